@@ -0,0 +1,210 @@
+use crate::priority::NotifyBehavior;
+use serde::{Deserialize, Serialize};
+
+/// 点击系统通知后的行为
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    /// 打开消息 extras 中的 `client::notification.click.url`，没有地址时定位到消息
+    #[default]
+    OpenUrl,
+    /// 打开主窗口并定位到消息
+    FocusMessage,
+}
+
+/// 针对单个应用的通知设置，在按优先级决定的提醒方式之后生效
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AppRule {
+    /// 只对该服务器生效，为空时对所有服务器上 id 相同的应用生效
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    pub appid: u64,
+    #[serde(default)]
+    pub click: ClickAction,
+    /// 不弹出系统通知，消息仍计入未读
+    #[serde(default)]
+    pub muted: bool,
+    /// 静音结束的时间（Unix 毫秒时间戳），为空时一直静音，用于“静音 2 小时”。
+    /// 只影响静音，点击行为、提示音等其他设置一直有效
+    #[serde(default)]
+    pub muted_until: Option<u64>,
+    /// 低于该优先级的消息不弹出系统通知
+    #[serde(default)]
+    pub min_priority: Option<u32>,
+    /// 代替优先级设置中的提示音
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// 只保存到历史并直接标为已读
+    #[serde(default)]
+    pub store_only: bool,
+}
+
+impl AppRule {
+    fn same_target(&self, profile_id: Option<&str>, appid: u64) -> bool {
+        self.appid == appid && self.profile_id.as_deref() == profile_id
+    }
+
+    /// 静音是否仍然生效
+    pub fn is_muted(&self, now: u64) -> bool {
+        self.muted && self.muted_until.map_or(true, |until| until > now)
+    }
+
+    /// 按应用设置调整提醒方式
+    pub fn apply(&self, priority: u32, behavior: &mut NotifyBehavior) {
+        if self.is_muted(crate::status::now_millis())
+            || self.store_only
+            || self.min_priority.is_some_and(|min| priority < min)
+        {
+            behavior.silent = true;
+            behavior.bring_to_front = false;
+        }
+        if let Some(sound) = &self.sound {
+            behavior.sound = Some(sound.clone());
+        }
+    }
+}
+
+/// 查找应用的设置，指定了服务器的设置优先于对所有服务器生效的设置
+pub fn find<'a>(rules: &'a [AppRule], profile_id: &str, appid: u64) -> Option<&'a AppRule> {
+    rules
+        .iter()
+        .find(|rule| rule.same_target(Some(profile_id), appid))
+        .or_else(|| rules.iter().find(|rule| rule.same_target(None, appid)))
+}
+
+/// 新增或替换服务器和应用都相同的设置，空的提示音名称视为不替换提示音
+pub fn upsert(rules: &mut Vec<AppRule>, mut rule: AppRule) {
+    rule.sound = rule
+        .sound
+        .map(|sound| sound.trim().to_string())
+        .filter(|sound| !sound.is_empty());
+    match rules
+        .iter_mut()
+        .find(|existing| existing.same_target(rule.profile_id.as_deref(), rule.appid))
+    {
+        Some(existing) => *existing = rule,
+        None => rules.push(rule),
+    }
+}
+
+/// 删除设置，返回是否存在
+pub fn remove(rules: &mut Vec<AppRule>, profile_id: Option<&str>, appid: u64) -> bool {
+    let before = rules.len();
+    rules.retain(|rule| !rule.same_target(profile_id, appid));
+    rules.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(profile_id: Option<&str>, appid: u64) -> AppRule {
+        AppRule {
+            profile_id: profile_id.map(str::to_string),
+            appid,
+            click: ClickAction::default(),
+            muted: false,
+            muted_until: None,
+            min_priority: None,
+            sound: None,
+            store_only: false,
+        }
+    }
+
+    #[test]
+    fn mute_ends_at_muted_until() {
+        let mut muted = rule(None, 1);
+        assert!(!muted.is_muted(1_000));
+
+        muted.muted = true;
+        assert!(muted.is_muted(u64::MAX));
+
+        muted.muted_until = Some(2_000);
+        assert!(muted.is_muted(1_999));
+        assert!(!muted.is_muted(2_000));
+
+        // 没有勾选静音时，静音时长不起作用
+        muted.muted = false;
+        assert!(!muted.is_muted(1_000));
+    }
+
+    #[test]
+    fn ended_mute_keeps_other_settings() {
+        let mut app = rule(None, 1);
+        app.muted = true;
+        app.muted_until = Some(1);
+        app.click = ClickAction::FocusMessage;
+        app.sound = Some("bell".to_string());
+
+        let mut behavior = NotifyBehavior::default();
+        app.apply(5, &mut behavior);
+        assert!(!behavior.silent);
+        assert_eq!(behavior.sound.as_deref(), Some("bell"));
+
+        let rules = [app];
+        assert_eq!(
+            find(&rules, "home", 1).map(|rule| rule.click),
+            Some(ClickAction::FocusMessage)
+        );
+    }
+
+    #[test]
+    fn silences_by_store_only_and_min_priority() {
+        let mut app = rule(None, 1);
+        app.min_priority = Some(5);
+        let mut behavior = NotifyBehavior {
+            bring_to_front: true,
+            ..NotifyBehavior::default()
+        };
+        app.apply(4, &mut behavior);
+        assert!(behavior.silent && !behavior.bring_to_front);
+
+        let mut behavior = NotifyBehavior::default();
+        app.apply(5, &mut behavior);
+        assert!(!behavior.silent);
+
+        app.store_only = true;
+        app.apply(9, &mut behavior);
+        assert!(behavior.silent);
+    }
+
+    #[test]
+    fn profile_rule_takes_precedence() {
+        let mut global = rule(None, 1);
+        global.sound = Some("global".to_string());
+        let mut home = rule(Some("home"), 1);
+        home.sound = Some("home".to_string());
+        let rules = [global, home, rule(Some("office"), 2)];
+
+        let sound = |profile_id, appid| {
+            find(&rules, profile_id, appid).and_then(|rule| rule.sound.as_deref())
+        };
+        assert_eq!(sound("home", 1), Some("home"));
+        assert_eq!(sound("office", 1), Some("global"));
+        assert!(find(&rules, "home", 2).is_none());
+        assert!(find(&rules, "home", 3).is_none());
+    }
+
+    #[test]
+    fn upsert_replaces_same_target_and_trims_sound() {
+        let mut rules = Vec::new();
+        let mut home = rule(Some("home"), 1);
+        home.sound = Some("  ".to_string());
+        upsert(&mut rules, home);
+        upsert(&mut rules, rule(None, 1));
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].sound, None);
+
+        let mut replaced = rule(Some("home"), 1);
+        replaced.muted = true;
+        replaced.sound = Some(" bell ".to_string());
+        upsert(&mut rules, replaced);
+        assert_eq!(rules.len(), 2);
+        assert!(rules[0].muted);
+        assert_eq!(rules[0].sound.as_deref(), Some("bell"));
+
+        assert!(remove(&mut rules, None, 1));
+        assert!(!remove(&mut rules, None, 1));
+        assert_eq!(rules.len(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::profiles::ServerProfile;
+use crate::rest::{GotifyClient, RestError};
+use crate::GotifyMessage;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 收到未知应用的消息时，同一服务器在这段时间内最多刷新一次应用列表
+const UNKNOWN_APP_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Gotify 服务器上的一个应用
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AppInfo {
+    pub profile_id: String,
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    /// 服务器上的图标路径，变化时重新下载
+    pub image: String,
+    /// 本地缓存的图标文件，下载失败时为空
+    pub icon_path: Option<String>,
+}
+
+/// 各服务器的应用列表，缓存在 `.gotify_apps.json`，图标保存在 `app_icons` 目录
+#[derive(Default)]
+pub struct AppDirectory {
+    apps: Mutex<HashMap<String, BTreeMap<u64, AppInfo>>>,
+    /// 各服务器上次因未知应用而刷新的时间
+    last_refresh: Mutex<HashMap<String, Instant>>,
+}
+
+fn cache_path() -> String {
+    format!("{}/.gotify_apps.json", crate::get_config_dir())
+}
+
+fn icon_dir() -> String {
+    format!("{}/app_icons", crate::get_config_dir())
+}
+
+impl AppDirectory {
+    /// 读取磁盘上的缓存，没有缓存时为空
+    pub fn load() -> Self {
+        let apps = std::fs::read_to_string(cache_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            apps: Mutex::new(apps),
+            last_refresh: Mutex::default(),
+        }
+    }
+
+    fn save(&self) {
+        let content = serde_json::to_string_pretty(&*self.apps.lock().unwrap()).unwrap();
+        if let Err(e) = std::fs::write(cache_path(), content) {
+            eprintln!("❌ 保存应用列表失败: {}", e);
+        }
+    }
+
+    pub fn get(&self, profile_id: &str, appid: u64) -> Option<AppInfo> {
+        self.apps
+            .lock()
+            .unwrap()
+            .get(profile_id)
+            .and_then(|apps| apps.get(&appid))
+            .cloned()
+    }
+
+    /// 返回缓存的应用，`profile_id` 为空时返回所有服务器的应用
+    pub fn list(&self, profile_id: Option<&str>) -> Vec<AppInfo> {
+        let apps = self.apps.lock().unwrap();
+        apps.iter()
+            .filter(|(id, _)| profile_id.map_or(true, |profile_id| profile_id == id.as_str()))
+            .flat_map(|(_, apps)| apps.values().cloned())
+            .collect()
+    }
+
+    /// 从服务器重新获取应用列表，并下载新增或变化的图标
+    pub async fn refresh(&self, profile: &ServerProfile) -> Result<Vec<AppInfo>, RestError> {
+        let client = GotifyClient::new(&profile.connection)?;
+        let applications = client.applications().await?;
+
+        let mut apps = BTreeMap::new();
+        for application in applications {
+            let previous = self.get(&profile.id, application.id);
+            let icon_path = match previous {
+                Some(previous) if previous.image == application.image && previous.icon_path.is_some() => {
+                    previous.icon_path
+                }
+                _ => download_icon(&client, &profile.id, application.id, &application.image).await,
+            };
+            apps.insert(application.id, AppInfo {
+                profile_id: profile.id.clone(),
+                id: application.id,
+                name: application.name,
+                description: application.description,
+                image: application.image,
+                icon_path,
+            });
+        }
+
+        let list: Vec<AppInfo> = apps.values().cloned().collect();
+        self.apps.lock().unwrap().insert(profile.id.clone(), apps);
+        self.last_refresh
+            .lock()
+            .unwrap()
+            .insert(profile.id.clone(), Instant::now());
+        self.save();
+        println!("📇 {}: 已更新 {} 个应用", profile.name, list.len());
+        Ok(list)
+    }
+
+    /// 查找应用，未知时刷新一次应用列表（有频率限制）
+    pub async fn resolve(&self, profile: &ServerProfile, appid: u64) -> Option<AppInfo> {
+        if let Some(info) = self.get(&profile.id, appid) {
+            return Some(info);
+        }
+
+        // 开始刷新前就记下时间，同时到达的其他消息不会再发起刷新；失败也计入，避免每条消息都请求一次
+        {
+            let mut last_refresh = self.last_refresh.lock().unwrap();
+            if last_refresh
+                .get(&profile.id)
+                .is_some_and(|at| at.elapsed() < UNKNOWN_APP_REFRESH_INTERVAL)
+            {
+                return None;
+            }
+            last_refresh.insert(profile.id.clone(), Instant::now());
+        }
+
+        if let Err(e) = self.refresh(profile).await {
+            eprintln!("❌ 获取 {} 的应用列表失败: {}", profile.name, e);
+        }
+        self.get(&profile.id, appid)
+    }
+
+    /// 用缓存的应用名称和图标补全消息
+    pub fn annotate(&self, message: &mut GotifyMessage) {
+        if let Some(info) = self.get(&message.profile_id, message.appid) {
+            message.app_name = Some(info.name);
+            message.app_icon = info.icon_path;
+        }
+    }
+
+    /// 删除服务器配置时清除它的应用和图标
+    pub fn remove_profile(&self, profile_id: &str) {
+        let removed = self.apps.lock().unwrap().remove(profile_id);
+        if let Some(apps) = removed {
+            for icon_path in apps.values().filter_map(|app| app.icon_path.as_ref()) {
+                let _ = std::fs::remove_file(icon_path);
+            }
+            self.save();
+        }
+        self.last_refresh.lock().unwrap().remove(profile_id);
+    }
+}
+
+/// 下载图标保存到 `app_icons/{profile_id}-{appid}.{ext}`，失败时返回 None
+async fn download_icon(client: &GotifyClient, profile_id: &str, appid: u64, image: &str) -> Option<String> {
+    if image.is_empty() {
+        return None;
+    }
+
+    let bytes = match client.application_image(image).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("⚠️ 下载应用图标失败 ({}): {}", image, e);
+            return None;
+        }
+    };
+
+    let extension = std::path::Path::new(image)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("png");
+    let dir = icon_dir();
+    let path = format!("{}/{}-{}.{}", dir, profile_id, appid, extension);
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, &bytes).await
+    };
+    match written.await {
+        Ok(()) => Some(path),
+        Err(e) => {
+            eprintln!("❌ 保存应用图标失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 读取缓存的图标，转换为可以直接用在 `<img>` 中的 data URL
+pub fn icon_data_url(path: &str) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    let mime = match std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    };
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
@@ -0,0 +1,206 @@
+use crate::filters::FilterError;
+use crate::net::NetError;
+use crate::priority::PriorityError;
+use crate::proxy::ProxyError;
+use crate::rest::RestError;
+use crate::server_url::UrlError;
+use crate::store::StoreError;
+use crate::tls::{CertificateInfo, TlsError};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// 命令返回给前端的错误。
+///
+/// 序列化为 `{ code, message, details }`：`code` 是稳定的错误标识，前端据此区分处理；
+/// `message` 是可以直接展示的说明；`details` 携带与错误相关的结构化数据，没有时为 null。
+#[derive(Clone, Debug)]
+pub enum AppError {
+    /// 尚未配置服务器
+    NotConfigured,
+    /// 指定的服务器配置不存在
+    ProfileNotFound(String),
+    /// 服务器地址无效
+    InvalidUrl(UrlError),
+    /// 配置内容有误（Token 含非法字符、证书文件无法读取等）
+    InvalidConfig(String),
+    /// 过滤规则无效，附带出错的规则和位置
+    InvalidFilter(FilterError),
+    /// Token 无效或没有权限
+    Unauthorized(String),
+    /// 无法连接服务器，或连接中途断开
+    Network(String),
+    /// 请求超时
+    Timeout,
+    /// TLS 握手或证书校验失败，附带服务器出示的证书指纹
+    Tls {
+        message: String,
+        certificate: Option<CertificateInfo>,
+    },
+    /// 代理配置错误或代理拒绝连接
+    Proxy(String),
+    /// 服务器返回了无法处理的响应
+    Server(String),
+    /// WebSocket 连接已在运行
+    AlreadyRunning,
+    /// 读写本地文件失败
+    Io(String),
+    /// 消息数据库不可用或读写失败
+    Storage(String),
+    /// 窗口操作失败
+    Window(String),
+}
+
+impl AppError {
+    /// 稳定的错误标识
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotConfigured => "not_configured",
+            AppError::ProfileNotFound(_) => "profile_not_found",
+            AppError::InvalidUrl(_) => "invalid_url",
+            AppError::InvalidConfig(_) => "invalid_config",
+            AppError::InvalidFilter(_) => "invalid_filter",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Network(_) => "network",
+            AppError::Timeout => "timeout",
+            AppError::Tls { .. } => "tls",
+            AppError::Proxy(_) => "proxy",
+            AppError::Server(_) => "server",
+            AppError::AlreadyRunning => "already_running",
+            AppError::Io(_) => "io",
+            AppError::Storage(_) => "storage",
+            AppError::Window(_) => "window",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::InvalidUrl(e) => Some(serde_json::json!({ "kind": e.kind() })),
+            AppError::InvalidFilter(e) => Some(serde_json::json!({
+                "rule": e.rule(),
+                "field": e.field(),
+                "position": e.position(),
+            })),
+            AppError::Tls {
+                certificate: Some(certificate),
+                ..
+            } => Some(serde_json::json!({ "certificate": certificate })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotConfigured => write!(f, "尚未配置服务器，请先在设置中填写服务器地址和 Token"),
+            AppError::ProfileNotFound(id) => write!(f, "找不到服务器配置: {}", id),
+            AppError::InvalidUrl(e) => write!(f, "{}", e),
+            AppError::InvalidConfig(e) => write!(f, "{}", e),
+            AppError::InvalidFilter(e) => write!(f, "{}", e),
+            AppError::Unauthorized(e) => write!(f, "{}", e),
+            AppError::Network(e) => write!(f, "{}", e),
+            AppError::Timeout => write!(f, "连接超时，请检查服务器地址是否可访问"),
+            AppError::Tls { message, .. } => write!(f, "{}", message),
+            AppError::Proxy(e) => write!(f, "{}", e),
+            AppError::Server(e) => write!(f, "{}", e),
+            AppError::AlreadyRunning => write!(f, "WebSocket 连接已在运行"),
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Storage(e) => write!(f, "{}", e),
+            AppError::Window(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<UrlError> for AppError {
+    fn from(e: UrlError) -> Self {
+        AppError::InvalidUrl(e)
+    }
+}
+
+impl From<TlsError> for AppError {
+    fn from(e: TlsError) -> Self {
+        match e {
+            TlsError::Handshake(_) => AppError::Tls {
+                message: e.to_string(),
+                certificate: None,
+            },
+            TlsError::PinMismatch(ref actual) => AppError::Tls {
+                certificate: Some(actual.clone()),
+                message: e.to_string(),
+            },
+            e => AppError::InvalidConfig(e.to_string()),
+        }
+    }
+}
+
+impl From<ProxyError> for AppError {
+    fn from(e: ProxyError) -> Self {
+        AppError::Proxy(e.to_string())
+    }
+}
+
+impl From<FilterError> for AppError {
+    fn from(e: FilterError) -> Self {
+        AppError::InvalidFilter(e)
+    }
+}
+
+impl From<PriorityError> for AppError {
+    fn from(e: PriorityError) -> Self {
+        AppError::InvalidConfig(e.to_string())
+    }
+}
+
+impl From<NetError> for AppError {
+    fn from(e: NetError) -> Self {
+        match e {
+            NetError::InvalidUrl(e) => AppError::InvalidConfig(e),
+            NetError::Connect(_) => AppError::Network(e.to_string()),
+            NetError::Proxy(e) => e.into(),
+            NetError::Tls(e) => e.into(),
+        }
+    }
+}
+
+impl From<RestError> for AppError {
+    fn from(e: RestError) -> Self {
+        match e {
+            RestError::Net(e) => e.into(),
+            RestError::InvalidUrl(e) => e.into(),
+            RestError::Timeout => AppError::Timeout,
+            RestError::Status(status) if status.as_u16() == 401 || status.as_u16() == 403 => {
+                AppError::Unauthorized(format!("认证失败 ({})，请检查 Client Token", status))
+            }
+            RestError::Http(_) => AppError::Network(e.to_string()),
+            RestError::Status(_)
+            | RestError::Decode(_)
+            | RestError::TooLarge(_)
+            | RestError::NotImage(_) => AppError::Server(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<StoreError> for AppError {
+    fn from(e: StoreError) -> Self {
+        AppError::Storage(e.to_string())
+    }
+}
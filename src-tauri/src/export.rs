@@ -0,0 +1,281 @@
+use crate::apps::AppDirectory;
+use crate::profiles::ServerProfile;
+use crate::GotifyMessage;
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 导出文件的格式
+#[derive(Clone, Copy, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+/// 导出文件中的一条消息
+#[derive(Serialize, Debug)]
+pub struct ExportedMessage {
+    pub profile_id: String,
+    pub profile_name: String,
+    pub id: u64,
+    pub appid: u64,
+    pub app_name: String,
+    pub priority: u32,
+    pub priority_label: &'static str,
+    /// ISO 8601 格式的 UTC 时间
+    pub timestamp: String,
+    pub title: String,
+    pub message: String,
+}
+
+/// `export_messages` 的结果
+#[derive(Serialize, Debug)]
+pub struct ExportSummary {
+    pub path: String,
+    pub count: usize,
+}
+
+/// 与界面一致的优先级名称
+pub fn priority_label(priority: u32) -> &'static str {
+    match priority {
+        8.. => "高",
+        5..=7 => "中",
+        _ => "低",
+    }
+}
+
+/// 导出时使用的服务器名称和应用名称
+#[derive(Default)]
+pub struct ExportNames {
+    profiles: HashMap<String, String>,
+    apps: HashMap<(String, u64), String>,
+}
+
+impl ExportNames {
+    /// 从应用目录取得应用名称，有未知应用的服务器先刷新一次；仍然未知的应用用 id 代替名称
+    pub async fn collect(
+        profiles: &[ServerProfile],
+        apps: &AppDirectory,
+        messages: &[(GotifyMessage, i64)],
+    ) -> Self {
+        let mut names = Self::default();
+        for profile in profiles {
+            names.profiles.insert(profile.id.clone(), profile.name.clone());
+
+            let unknown = messages
+                .iter()
+                .any(|(m, _)| m.profile_id == profile.id && apps.get(&profile.id, m.appid).is_none());
+            if unknown {
+                if let Err(e) = apps.refresh(profile).await {
+                    eprintln!("⚠️ 获取 {} 的应用列表失败，导出时使用应用 id: {}", profile.name, e);
+                }
+            }
+            for app in apps.list(Some(&profile.id)) {
+                names.apps.insert((profile.id.clone(), app.id), app.name);
+            }
+        }
+        names
+    }
+
+    fn profile(&self, profile_id: &str) -> String {
+        self.profiles
+            .get(profile_id)
+            .cloned()
+            .unwrap_or_else(|| profile_id.to_string())
+    }
+
+    fn app(&self, profile_id: &str, appid: u64) -> String {
+        self.apps
+            .get(&(profile_id.to_string(), appid))
+            .cloned()
+            .unwrap_or_else(|| format!("应用 {}", appid))
+    }
+}
+
+/// 消息涉及的服务器 id，用于只查询需要的服务器
+pub fn profile_ids(messages: &[(GotifyMessage, i64)]) -> HashSet<String> {
+    messages.iter().map(|(message, _)| message.profile_id.clone()).collect()
+}
+
+pub fn build(messages: Vec<(GotifyMessage, i64)>, names: &ExportNames) -> Vec<ExportedMessage> {
+    messages
+        .into_iter()
+        .map(|(message, timestamp)| ExportedMessage {
+            profile_name: names.profile(&message.profile_id),
+            app_name: names.app(&message.profile_id, message.appid),
+            priority_label: priority_label(message.priority),
+            timestamp: iso_timestamp(timestamp),
+            profile_id: message.profile_id,
+            id: message.id,
+            appid: message.appid,
+            priority: message.priority,
+            title: message.title,
+            message: message.message,
+        })
+        .collect()
+}
+
+fn iso_timestamp(millis: i64) -> String {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// 把消息写入文件，已存在的文件会被覆盖
+pub fn write(path: &Path, format: ExportFormat, messages: &[ExportedMessage]) -> io::Result<()> {
+    let mut writer = BufWriter::new(std::fs::File::create(path)?);
+    match format {
+        ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, messages)?,
+        ExportFormat::Csv => write_csv(&mut writer, messages)?,
+        ExportFormat::Markdown => write_markdown(&mut writer, messages)?,
+    }
+    writer.flush()
+}
+
+fn write_csv(writer: &mut impl Write, messages: &[ExportedMessage]) -> io::Result<()> {
+    // 带 BOM，Excel 才能正确识别中文
+    writer.write_all("\u{feff}".as_bytes())?;
+    writeln!(writer, "timestamp,profile,app_id,app,priority,priority_label,id,title,message")?;
+    for message in messages {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            message.timestamp,
+            csv_field(&message.profile_name),
+            message.appid,
+            csv_field(&message.app_name),
+            message.priority,
+            message.priority_label,
+            message.id,
+            csv_field(&message.title),
+            csv_field(&message.message)
+        )?;
+    }
+    Ok(())
+}
+
+/// 按 RFC 4180 转义：包含逗号、引号或换行时加引号，引号写两次。
+///
+/// 标题和正文可以由任何持有应用 Token 的人发送，以公式字符开头的值前面加 `'`，
+/// 避免在电子表格中被当作公式执行
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn write_markdown(writer: &mut impl Write, messages: &[ExportedMessage]) -> io::Result<()> {
+    writeln!(writer, "# Gotify 消息报告")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "导出时间：{}  ",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    )?;
+    if let (Some(first), Some(last)) = (messages.first(), messages.last()) {
+        writeln!(writer, "时间范围：{} ~ {}  ", first.timestamp, last.timestamp)?;
+    }
+    writeln!(writer, "消息总数：{}", messages.len())?;
+
+    // 按应用汇总，便于先看清哪些应用告警最多
+    let mut by_app: BTreeMap<(&str, &str), [usize; 3]> = BTreeMap::new();
+    for message in messages {
+        let counts = by_app
+            .entry((message.profile_name.as_str(), message.app_name.as_str()))
+            .or_default();
+        match message.priority_label {
+            "高" => counts[0] += 1,
+            "中" => counts[1] += 1,
+            _ => counts[2] += 1,
+        }
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "## 按应用汇总")?;
+    writeln!(writer)?;
+    writeln!(writer, "| 服务器 | 应用 | 高 | 中 | 低 |")?;
+    writeln!(writer, "| --- | --- | ---: | ---: | ---: |")?;
+    for ((profile, app), [high, medium, low]) in &by_app {
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} | {} |",
+            markdown_cell(profile),
+            markdown_cell(app),
+            high,
+            medium,
+            low
+        )?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "## 消息列表")?;
+    writeln!(writer)?;
+    writeln!(writer, "| 时间 | 服务器 | 应用 | 优先级 | 标题 | 内容 |")?;
+    writeln!(writer, "| --- | --- | --- | --- | --- | --- |")?;
+    for message in messages {
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} ({}) | {} | {} |",
+            message.timestamp,
+            markdown_cell(&message.profile_name),
+            markdown_cell(&message.app_name),
+            message.priority_label,
+            message.priority,
+            markdown_cell(&message.title),
+            markdown_cell(&message.message)
+        )?;
+    }
+    Ok(())
+}
+
+/// 表格单元格内不能出现竖线和换行
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields_when_needed() {
+        assert_eq!(csv_field("磁盘告警"), "磁盘告警");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("说 \"你好\""), "\"说 \"\"你好\"\"\"");
+        assert_eq!(csv_field("第一行\n第二行"), "\"第一行\n第二行\"");
+    }
+
+    #[test]
+    fn neutralizes_csv_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"http://x\")"), "\"'=HYPERLINK(\"\"http://x\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-2"), "'-2");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("\t=1"), "'\t=1");
+        assert_eq!(csv_field("\r=1"), "\"'\r=1\"");
+        // 只检查开头的字符
+        assert_eq!(csv_field("a=b"), "a=b");
+    }
+
+    #[test]
+    fn escapes_markdown_cells() {
+        assert_eq!(markdown_cell("a | b"), "a \\| b");
+        assert_eq!(markdown_cell("一\r\n二\n三\r四"), "一<br>二<br>三<br>四");
+        assert_eq!(markdown_cell("\"引号\" 不变"), "\"引号\" 不变");
+    }
+}
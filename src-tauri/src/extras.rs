@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Gotify 消息的 `extras`。
+///
+/// 原始内容保存在 `raw` 中，序列化时原样输出，不认识的命名空间也不会丢失；
+/// 客户端用到的字段另外解析为类型化的结构，格式不对的字段按缺省处理。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Map<String, Value>", into = "Map<String, Value>")]
+pub struct MessageExtras {
+    pub display: DisplayExtras,
+    pub notification: NotificationExtras,
+    raw: Map<String, Value>,
+}
+
+/// `client::display`
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DisplayExtras {
+    /// `text/plain` 或 `text/markdown`
+    pub content_type: Option<String>,
+}
+
+/// `client::notification`
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NotificationExtras {
+    pub click: ClickExtras,
+    /// 在通知中显示的大图
+    pub big_image_url: Option<String>,
+}
+
+/// `client::notification.click`
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClickExtras {
+    /// 点击通知时打开的地址
+    pub url: Option<String>,
+}
+
+fn namespace<T: for<'de> Deserialize<'de> + Default>(raw: &Map<String, Value>, key: &str) -> T {
+    raw.get(key)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+impl From<Map<String, Value>> for MessageExtras {
+    fn from(raw: Map<String, Value>) -> Self {
+        Self {
+            display: namespace(&raw, "client::display"),
+            notification: namespace(&raw, "client::notification"),
+            raw,
+        }
+    }
+}
+
+impl From<MessageExtras> for Map<String, Value> {
+    fn from(extras: MessageExtras) -> Self {
+        extras.raw
+    }
+}
+
+impl MessageExtras {
+    pub fn is_markdown(&self) -> bool {
+        self.display.content_type.as_deref() == Some("text/markdown")
+    }
+
+    /// 点击地址，只接受 http 和 https
+    pub fn click_url(&self) -> Option<url::Url> {
+        web_url(self.notification.click.url.as_deref()?)
+    }
+
+    pub fn big_image_url(&self) -> Option<url::Url> {
+        web_url(self.notification.big_image_url.as_deref()?)
+    }
+
+    /// 原始的 extras，按命名空间索引
+    pub fn raw(&self) -> &Map<String, Value> {
+        &self.raw
+    }
+
+    /// 存入数据库的 JSON
+    pub fn to_json(&self) -> String {
+        Value::Object(self.raw.clone()).to_string()
+    }
+}
+
+fn web_url(input: &str) -> Option<url::Url> {
+    url::Url::parse(input)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// 把 Markdown 转换为适合系统通知显示的纯文本：去掉标记符号，链接和图片只保留文字
+pub fn markdown_to_plain(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_string());
+            continue;
+        }
+        // 分隔线
+        if trimmed.len() >= 3 && trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | ' ')) {
+            continue;
+        }
+
+        let text = strip_heading(trimmed.trim_start_matches('>').trim_start());
+        let list_item = ["- [ ] ", "- [x] ", "- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| text.strip_prefix(marker));
+        lines.push(match list_item {
+            Some(item) => format!("• {}", strip_inline(item)),
+            None => strip_inline(text),
+        });
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+/// 去掉 1 到 6 个 `#` 加空格的标题前缀，`#deploy` 这样的标签保持不变
+fn strip_heading(line: &str) -> &str {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[hashes..];
+    if (1..=6).contains(&hashes) && (rest.is_empty() || rest.starts_with(' ')) {
+        rest.trim_start()
+    } else {
+        line
+    }
+}
+
+/// 去掉行内成对的强调、代码和链接标记，不成对的符号原样保留
+fn strip_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            marker @ ('*' | '_' | '~' | '`') => {
+                let run = run_length(&chars, i);
+                // 单个下划线常见于标识符，单个 ~ 也不是删除线
+                let paired = if matches!(marker, '_' | '~') && run < 2 {
+                    None
+                } else {
+                    closing_marker(&chars, i + run, run)
+                };
+                match paired {
+                    Some(close) => {
+                        let inner: String = chars[i + run..close].iter().collect();
+                        // 行内代码的内容原样保留
+                        if marker == '`' {
+                            out.push_str(&inner);
+                        } else {
+                            out.push_str(&strip_inline(&inner));
+                        }
+                        i = close + run;
+                    }
+                    None => {
+                        out.extend(&chars[i..i + run]);
+                        i += run;
+                    }
+                }
+            }
+            // [文字](地址) 和 ![说明](地址)
+            '!' | '[' => {
+                let start = if chars[i] == '!' { i + 1 } else { i };
+                match link_end(&chars, start) {
+                    Some((label_end, end)) => {
+                        out.push_str(&strip_inline(&chars[start + 1..label_end].iter().collect::<String>()));
+                        i = end;
+                    }
+                    None => {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                }
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// 从 `start` 开始连续相同字符的个数
+fn run_length(chars: &[char], start: usize) -> usize {
+    chars[start..].iter().take_while(|&&c| c == chars[start]).count()
+}
+
+/// 查找与开头标记（位于 `start - run`）长度相同的结束标记，标记内侧不能是空白
+fn closing_marker(chars: &[char], start: usize, run: usize) -> Option<usize> {
+    let marker = chars[start - run];
+    if chars.get(start).map_or(true, |c| c.is_whitespace()) {
+        return None;
+    }
+    let mut j = start;
+    while j < chars.len() {
+        if chars[j] == marker {
+            let length = run_length(chars, j);
+            if length == run && !chars[j - 1].is_whitespace() {
+                return Some(j);
+            }
+            j += length;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// `chars[start]` 为 `[` 时查找 `](...)`，返回 `]` 的位置和整个链接之后的位置
+fn link_end(chars: &[char], start: usize) -> Option<(usize, usize)> {
+    if chars.get(start) != Some(&'[') {
+        return None;
+    }
+    let label_end = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+    let url_end = label_end + 1 + chars[label_end + 1..].iter().position(|&c| c == ')')?;
+    Some((label_end, url_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_paired_emphasis_only() {
+        assert_eq!(markdown_to_plain("**粗体** 和 *斜体*"), "粗体 和 斜体");
+        assert_eq!(markdown_to_plain("__粗体__ ~~删除~~"), "粗体 删除");
+        assert_eq!(markdown_to_plain("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(markdown_to_plain("a ** b"), "a ** b");
+        assert_eq!(markdown_to_plain("**未闭合"), "**未闭合");
+        assert_eq!(markdown_to_plain("snake_case_name ~home"), "snake_case_name ~home");
+        assert_eq!(markdown_to_plain("\\*转义\\*"), "*转义*");
+    }
+
+    #[test]
+    fn keeps_inline_code_verbatim() {
+        assert_eq!(markdown_to_plain("运行 `a * b` 和 `**x**`"), "运行 a * b 和 **x**");
+    }
+
+    #[test]
+    fn strips_headings_but_not_hashtags() {
+        assert_eq!(markdown_to_plain("# 标题\n## 二级"), "标题\n二级");
+        assert_eq!(markdown_to_plain("#deploy 完成"), "#deploy 完成");
+        assert_eq!(markdown_to_plain("####### 太多"), "####### 太多");
+        assert_eq!(markdown_to_plain("> # 引用标题"), "引用标题");
+    }
+
+    #[test]
+    fn reduces_links_lists_and_code_blocks() {
+        assert_eq!(
+            markdown_to_plain("查看 [详情](https://example.com) ![图](a.png)"),
+            "查看 详情 图"
+        );
+        assert_eq!(markdown_to_plain("- 一\n* 二\n- [x] 三"), "• 一\n• 二\n• 三");
+        assert_eq!(markdown_to_plain("```\n*原样*\n```\n---\n结束"), "*原样*\n结束");
+    }
+
+    #[test]
+    fn parses_known_extras() {
+        let extras: MessageExtras = serde_json::from_str(
+            r#"{
+                "client::display": { "contentType": "text/markdown" },
+                "client::notification": { "click": { "url": "https://example.com" }, "bigImageUrl": "file:///etc/passwd" },
+                "custom::ns": { "a": 1 }
+            }"#,
+        )
+        .unwrap();
+        assert!(extras.is_markdown());
+        assert_eq!(extras.click_url().unwrap().as_str(), "https://example.com/");
+        assert!(extras.big_image_url().is_none());
+        assert!(extras.to_json().contains("custom::ns"));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::GotifyMessage;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 一条过滤规则。规则按顺序执行，条件全部满足时执行其动作
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct FilterRule {
+    /// 界面上显示的名称，也用于错误提示
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub when: FilterConditions,
+    #[serde(default)]
+    pub actions: Vec<FilterAction>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 匹配条件，未填写的条件不参与匹配；正则表达式默认区分大小写，可以用 `(?i)` 忽略大小写
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct FilterConditions {
+    /// 标题匹配的正则表达式
+    pub title: Option<String>,
+    /// 正文匹配的正则表达式
+    pub message: Option<String>,
+    /// 标题或正文包含其中任意一个关键词（不区分大小写）
+    pub keywords: Vec<String>,
+    pub profile_id: Option<String>,
+    pub appid: Option<u64>,
+    pub min_priority: Option<u32>,
+    pub max_priority: Option<u32>,
+    /// extras 字段的条件，全部满足才算匹配
+    pub extras: Vec<ExtrasCondition>,
+}
+
+/// extras 中某个字段匹配正则表达式，字段不存在时不匹配
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ExtrasCondition {
+    /// 命名空间和字段之间用 `.` 分隔，如 `client::notification.click.url`
+    pub path: String,
+    /// 字段值匹配的正则表达式，非字符串的值先转换为 JSON 文本
+    pub pattern: String,
+}
+
+/// 规则匹配后执行的动作
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterAction {
+    /// 不弹出系统通知，消息仍然保存并显示在列表中
+    Suppress,
+    /// 修改消息的优先级，后面的规则和提醒方式都使用新的优先级
+    SetPriority { priority: u32 },
+    /// 使用指定的提示音，代替优先级和应用设置中的提示音
+    Sound { name: String },
+    /// 给消息添加标签
+    Tag { tag: String },
+    /// 在列表中用指定颜色（`#rrggbb`）突出显示
+    Highlight { color: String },
+    /// 不再执行后面的规则
+    Stop,
+}
+
+/// 规则对提醒方式的影响，标签、高亮和优先级直接写入消息
+#[derive(Debug, Default)]
+pub struct FilterOutcome {
+    pub suppress: bool,
+    pub sound: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FilterError {
+    /// 正则表达式无效。`position` 是出错位置在表达式中的字符序号（从 1 开始）
+    InvalidRegex {
+        rule: usize,
+        field: String,
+        position: Option<usize>,
+        message: String,
+    },
+    /// 动作的参数无效
+    InvalidAction { rule: usize, message: String },
+}
+
+impl FilterError {
+    /// 出错规则的序号（从 1 开始）
+    pub fn rule(&self) -> usize {
+        match self {
+            FilterError::InvalidRegex { rule, .. } | FilterError::InvalidAction { rule, .. } => *rule,
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            FilterError::InvalidRegex { field, .. } => Some(field),
+            FilterError::InvalidAction { .. } => None,
+        }
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            FilterError::InvalidRegex { position, .. } => *position,
+            FilterError::InvalidAction { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::InvalidRegex {
+                rule,
+                field,
+                position: Some(position),
+                message,
+            } => write!(f, "第 {} 条规则的 {} 正则表达式在第 {} 个字符处有误: {}", rule, field, position, message),
+            FilterError::InvalidRegex { rule, field, message, .. } => {
+                write!(f, "第 {} 条规则的 {} 正则表达式无效: {}", rule, field, message)
+            }
+            FilterError::InvalidAction { rule, message } => write!(f, "第 {} 条规则的动作无效: {}", rule, message),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// 编译后的规则，保存配置时生成，处理消息时不再重复编译
+#[derive(Default)]
+pub struct FilterSet {
+    rules: Vec<CompiledRule>,
+}
+
+struct CompiledRule {
+    title: Option<Regex>,
+    message: Option<Regex>,
+    keywords: Vec<String>,
+    profile_id: Option<String>,
+    appid: Option<u64>,
+    min_priority: Option<u32>,
+    max_priority: Option<u32>,
+    extras: Vec<(Vec<String>, Regex)>,
+    actions: Vec<FilterAction>,
+}
+
+impl FilterSet {
+    /// 校验并编译规则，停用的规则也会校验但不参与匹配
+    pub fn compile(rules: &[FilterRule]) -> Result<Self, FilterError> {
+        let mut compiled = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            let number = index + 1;
+            let regex = |field: &str, pattern: &Option<String>| {
+                pattern
+                    .as_deref()
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(|pattern| compile_regex(number, field, pattern))
+                    .transpose()
+            };
+            let title = regex("title", &rule.when.title)?;
+            let message = regex("message", &rule.when.message)?;
+            let extras = rule
+                .when
+                .extras
+                .iter()
+                .map(|condition| {
+                    let field = format!("extras.{}", condition.path);
+                    let path = split_path(&condition.path);
+                    compile_regex(number, &field, &condition.pattern).map(|regex| (path, regex))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for action in &rule.actions {
+                validate_action(number, action)?;
+            }
+
+            if rule.enabled {
+                compiled.push(CompiledRule {
+                    title,
+                    message,
+                    keywords: rule
+                        .when
+                        .keywords
+                        .iter()
+                        .map(|keyword| keyword.trim().to_lowercase())
+                        .filter(|keyword| !keyword.is_empty())
+                        .collect(),
+                    profile_id: rule.when.profile_id.clone(),
+                    appid: rule.when.appid,
+                    min_priority: rule.when.min_priority,
+                    max_priority: rule.when.max_priority,
+                    extras,
+                    actions: rule.actions.clone(),
+                });
+            }
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    /// 按顺序执行规则，修改消息的优先级、标签和高亮，返回对提醒方式的影响
+    pub fn apply(&self, message: &mut GotifyMessage) -> FilterOutcome {
+        let mut outcome = FilterOutcome::default();
+        for rule in &self.rules {
+            if !rule.matches(message) {
+                continue;
+            }
+            for action in &rule.actions {
+                match action {
+                    FilterAction::Suppress => outcome.suppress = true,
+                    FilterAction::SetPriority { priority } => message.priority = *priority,
+                    FilterAction::Sound { name } => outcome.sound = Some(name.clone()),
+                    FilterAction::Tag { tag } => {
+                        if !message.tags.contains(tag) {
+                            message.tags.push(tag.clone());
+                        }
+                    }
+                    FilterAction::Highlight { color } => message.highlight = Some(color.clone()),
+                    FilterAction::Stop => return outcome,
+                }
+            }
+        }
+        outcome
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, message: &GotifyMessage) -> bool {
+        if self.profile_id.as_ref().is_some_and(|id| *id != message.profile_id)
+            || self.appid.is_some_and(|appid| appid != message.appid)
+            || self.min_priority.is_some_and(|min| message.priority < min)
+            || self.max_priority.is_some_and(|max| message.priority > max)
+        {
+            return false;
+        }
+        if self.title.as_ref().is_some_and(|regex| !regex.is_match(&message.title))
+            || self.message.as_ref().is_some_and(|regex| !regex.is_match(&message.message))
+        {
+            return false;
+        }
+        if !self.keywords.is_empty() {
+            let text = format!("{}\n{}", message.title, message.message).to_lowercase();
+            if !self.keywords.iter().any(|keyword| text.contains(keyword.as_str())) {
+                return false;
+            }
+        }
+
+        let extras = message.extras.as_ref().map(|extras| extras.raw());
+        self.extras.iter().all(|(path, regex)| {
+            let Some(mut value) = extras.and_then(|extras| extras.get(&path[0])) else {
+                return false;
+            };
+            for key in &path[1..] {
+                match value.get(key) {
+                    Some(next) => value = next,
+                    None => return false,
+                }
+            }
+            match value {
+                serde_json::Value::String(text) => regex.is_match(text),
+                value => regex.is_match(&value.to_string()),
+            }
+        })
+    }
+}
+
+/// `client::notification.click.url` 拆分为 `["client::notification", "click", "url"]`
+fn split_path(path: &str) -> Vec<String> {
+    path.split('.').map(str::to_string).collect()
+}
+
+fn compile_regex(rule: usize, field: &str, pattern: &str) -> Result<Regex, FilterError> {
+    let invalid = |position, message| FilterError::InvalidRegex {
+        rule,
+        field: field.to_string(),
+        position,
+        message,
+    };
+
+    // regex 的错误只有格式化后的文本，先用 regex-syntax 解析以取得出错位置
+    if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+        let (offset, message) = match &e {
+            regex_syntax::Error::Parse(e) => (e.span().start.offset, e.kind().to_string()),
+            regex_syntax::Error::Translate(e) => (e.span().start.offset, e.kind().to_string()),
+            e => (0, e.to_string()),
+        };
+        let position = pattern.get(..offset).map_or(0, |prefix| prefix.chars().count()) + 1;
+        return Err(invalid(Some(position), message));
+    }
+    Regex::new(pattern).map_err(|e| invalid(None, e.to_string()))
+}
+
+fn validate_action(rule: usize, action: &FilterAction) -> Result<(), FilterError> {
+    let invalid = |message: String| Err(FilterError::InvalidAction { rule, message });
+    match action {
+        FilterAction::Sound { name } if name.trim().is_empty() => invalid("提示音名称不能为空".to_string()),
+        FilterAction::Tag { tag } if tag.trim().is_empty() => invalid("标签不能为空".to_string()),
+        FilterAction::Highlight { color } if !is_hex_color(color) => {
+            invalid(format!("高亮颜色 {} 无效，应为 #rrggbb 格式", color))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex_error(pattern: &str) -> FilterError {
+        compile_regex(2, "title", pattern).unwrap_err()
+    }
+
+    fn message(title: &str, priority: u32) -> GotifyMessage {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "appid": 3,
+            "title": title,
+            "message": "",
+            "priority": priority,
+            "date": "2024-01-01T00:00:00Z",
+            "profile_id": "home",
+        }))
+        .unwrap()
+    }
+
+    fn rule(title: &str, actions: Vec<FilterAction>) -> FilterRule {
+        FilterRule {
+            name: String::new(),
+            enabled: true,
+            when: FilterConditions {
+                title: Some(title.to_string()),
+                ..FilterConditions::default()
+            },
+            actions,
+        }
+    }
+
+    #[test]
+    fn reports_error_position_in_characters() {
+        let error = regex_error("abc(");
+        assert_eq!((error.rule(), error.field(), error.position()), (2, Some("title"), Some(4)));
+
+        // 中文字符按一个字符计数，而不是 UTF-8 字节数
+        assert_eq!(regex_error("中文[z-a]").position(), Some(4));
+        assert_eq!(regex_error("(?i)备份)").position(), Some(7));
+        assert!(regex_error("a{2,1}").to_string().starts_with("第 2 条规则的 title 正则表达式在第"));
+    }
+
+    #[test]
+    fn compile_reports_rule_and_field() {
+        let mut broken = rule("ok", Vec::new());
+        broken.enabled = false;
+        broken.when.extras.push(ExtrasCondition {
+            path: "client::display.contentType".to_string(),
+            pattern: "[".to_string(),
+        });
+        let Err(error) = FilterSet::compile(&[rule("ok", Vec::new()), broken]) else {
+            panic!("停用的规则也应当校验");
+        };
+        assert_eq!(error.rule(), 2);
+        assert_eq!(error.field(), Some("extras.client::display.contentType"));
+        assert_eq!(error.position(), Some(1));
+
+        let highlight = rule("", vec![FilterAction::Highlight { color: "red".to_string() }]);
+        let Err(error) = FilterSet::compile(&[highlight]) else {
+            panic!("高亮颜色应当校验");
+        };
+        assert!(matches!(error, FilterError::InvalidAction { rule: 1, .. }));
+    }
+
+    #[test]
+    fn applies_actions_in_order_until_stop() {
+        let filters = FilterSet::compile(&[
+            rule("(?i)backup", vec![
+                FilterAction::SetPriority { priority: 8 },
+                FilterAction::Tag { tag: "备份".to_string() },
+            ]),
+            FilterRule {
+                when: FilterConditions {
+                    min_priority: Some(8),
+                    ..FilterConditions::default()
+                },
+                ..rule("", vec![
+                    FilterAction::Highlight { color: "#ff0000".to_string() },
+                    FilterAction::Stop,
+                ])
+            },
+            rule("", vec![FilterAction::Suppress]),
+        ])
+        .unwrap();
+
+        let mut backup = message("Nightly BACKUP done", 2);
+        let outcome = filters.apply(&mut backup);
+        assert_eq!(backup.priority, 8);
+        assert_eq!(backup.tags, ["备份"]);
+        assert_eq!(backup.highlight.as_deref(), Some("#ff0000"));
+        assert!(!outcome.suppress);
+
+        let mut other = message("disk full", 2);
+        assert!(filters.apply(&mut other).suppress);
+        assert!(other.tags.is_empty());
+    }
+}
@@ -1,11 +1,55 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager, State};
 use tauri::tray::{TrayIconBuilder, TrayIcon};
-use futures_util::StreamExt;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame, Message};
+use tokio_util::sync::CancellationToken;
 use image::GenericImageView;
 
+mod app_rules;
+mod apps;
+mod error;
+mod export;
+mod extras;
+mod filters;
+mod net;
+mod notifier;
+mod priority;
+mod profiles;
+mod proxy;
+mod reconnect;
+mod retention;
+mod rest;
+mod server_url;
+mod status;
+mod store;
+mod sync;
+mod tls;
+
+use reconnect::ReconnectPolicy;
+use retention::{PruneReport, RetentionPolicy};
+use app_rules::AppRule;
+use apps::{AppDirectory, AppInfo};
+use error::AppError;
+use export::{ExportFormat, ExportNames, ExportSummary};
+use extras::MessageExtras;
+use filters::{FilterRule, FilterSet};
+use net::NetError;
+use priority::PriorityMap;
+use profiles::{AppConfig, ProfileUpdate, ServerProfile, DEFAULT_PROFILE_ID};
+use proxy::ProxyOptions;
+use server_url::ServerUrl;
+use status::{ConnectionState, ConnectionStatus, StatusRegistry, StatusTracker};
+use store::{MessageFilter, MessagePage, MessageStore, SearchResults, StoreStats, UnreadCounts};
+use sync::{CatchUpPolicy, ImportPolicy};
+use tls::{CertificateInfo, TlsError, TlsOptions};
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct GotifyMessage {
     id: u64,
@@ -14,12 +58,90 @@ struct GotifyMessage {
     title: String,
     priority: u32,
     date: String,
+    /// Gotify 的 extras，例如 `client::display` 和 `client::notification`
+    #[serde(default)]
+    extras: Option<MessageExtras>,
+    /// 消息来自哪个服务器配置，由客户端在收到消息时填写
+    #[serde(default)]
+    profile_id: String,
+    /// 本地标记：保留该消息，不受保留策略清理
+    #[serde(default)]
+    keep: bool,
+    /// 本地标记：消息是否已读
+    #[serde(default)]
+    read: bool,
+    /// 发送消息的应用名称，来自应用目录
+    #[serde(default)]
+    app_name: Option<String>,
+    /// 应用图标在本地缓存中的路径
+    #[serde(default)]
+    app_icon: Option<String>,
+    /// 过滤规则添加的标签
+    #[serde(default)]
+    tags: Vec<String>,
+    /// 过滤规则设置的高亮颜色（`#rrggbb`）
+    #[serde(default)]
+    highlight: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct ConnectionConfig {
     server_url: String,
     client_token: String,
+    #[serde(default)]
+    reconnect: ReconnectPolicy,
+    /// 客户端发送 Ping 的间隔（秒），0 表示关闭心跳
+    #[serde(default = "default_ping_interval_secs")]
+    ping_interval_secs: u64,
+    /// 发送 Ping 后等待 Pong 的最长时间（秒），超时视为连接已失效
+    #[serde(default = "default_pong_timeout_secs")]
+    pong_timeout_secs: u64,
+    #[serde(default)]
+    catch_up: CatchUpPolicy,
+    #[serde(default)]
+    import: ImportPolicy,
+    #[serde(default)]
+    token_transport: TokenTransport,
+    #[serde(default)]
+    tls: TlsOptions,
+    #[serde(default)]
+    proxy: ProxyOptions,
+}
+
+/// Client Token 的传递方式
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TokenTransport {
+    /// 通过 X-Gotify-Key 请求头传递（默认）
+    #[default]
+    Header,
+    /// 通过 `?token=` 查询参数传递，兼容会剥离自定义请求头的旧代理
+    Query,
+}
+
+fn default_ping_interval_secs() -> u64 {
+    30
+}
+
+fn default_pong_timeout_secs() -> u64 {
+    10
+}
+
+impl ConnectionConfig {
+    fn new(server_url: String, client_token: String) -> Self {
+        Self {
+            server_url,
+            client_token,
+            reconnect: ReconnectPolicy::default(),
+            ping_interval_secs: default_ping_interval_secs(),
+            pong_timeout_secs: default_pong_timeout_secs(),
+            catch_up: CatchUpPolicy::default(),
+            import: ImportPolicy::default(),
+            token_transport: TokenTransport::default(),
+            tls: TlsOptions::default(),
+            proxy: ProxyOptions::default(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -30,9 +152,29 @@ struct WindowPosition {
     height: u32,
 }
 
+/// 正在运行的 WebSocket 任务
+struct WsTask {
+    cancel: CancellationToken,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl WsTask {
+    fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+}
+
 struct AppState {
-    config: Arc<Mutex<Option<ConnectionConfig>>>,
-    ws_running: Arc<Mutex<bool>>,
+    config: Arc<Mutex<AppConfig>>,
+    /// 每个服务器配置一个连接任务，按配置 id 索引
+    ws_tasks: Arc<Mutex<HashMap<String, WsTask>>>,
+    status: Arc<StatusRegistry>,
+    /// 本地消息历史，数据库无法打开时为 None，此时只推送不保存
+    store: Option<Arc<MessageStore>>,
+    /// 各服务器的应用名称和图标
+    apps: Arc<AppDirectory>,
+    /// 编译后的过滤规则，保存规则时替换，不需要重新连接
+    filters: Arc<Mutex<FilterSet>>,
     window_position: Arc<Mutex<Option<WindowPosition>>>,
     tray_icon: Arc<Mutex<Option<TrayIcon>>>,
 }
@@ -51,44 +193,94 @@ fn get_config_dir() -> String {
     }
 }
 
+/// 用界面填写的地址和 Token 生成配置，保留已有的高级设置
+fn config_with_credentials(
+    existing: Option<&ConnectionConfig>,
+    server_url: String,
+    client_token: String,
+) -> ConnectionConfig {
+    match existing {
+        Some(existing) => ConnectionConfig {
+            server_url,
+            client_token,
+            ..existing.clone()
+        },
+        None => ConnectionConfig::new(server_url, client_token),
+    }
+}
+
+/// 把修改后的配置写入文件
+fn persist_config(config: &AppConfig) -> Result<(), AppError> {
+    profiles::save_config(config).map_err(|e| {
+        eprintln!("❌ 保存配置失败: {}", e);
+        AppError::Io(format!("保存配置失败: {}", e))
+    })
+}
+
+/// 保存服务器地址和连接设置。没有指定 `profile_id` 时保存到第一个服务器，还没有服务器时新建一个
 #[tauri::command]
 async fn save_config_to_file(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     server_url: String,
     client_token: String,
-) -> Result<String, String> {
-    let config = ConnectionConfig {
-        server_url,
-        client_token,
-    };
+    tls: Option<TlsOptions>,
+    proxy: Option<ProxyOptions>,
+    profile_id: Option<String>,
+) -> Result<String, AppError> {
+    // 保存前校验并规范化服务器地址
+    let server_url = ServerUrl::parse(&server_url)?.normalized();
+
+    let (profile, server_changed) = {
+        let mut app_config = state.config.lock().unwrap();
+        if app_config.profiles.is_empty() && profile_id.is_none() {
+            let color = app_config.next_color();
+            app_config.profiles.push(ServerProfile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: "默认服务器".to_string(),
+                color,
+                enabled: true,
+                connection: ConnectionConfig::new(server_url.clone(), client_token.clone()),
+            });
+        }
 
-    let mut state_config = state.config.lock().unwrap();
-    *state_config = Some(config.clone());
+        let profile = app_config
+            .resolve_mut(profile_id.as_deref())
+            .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone().unwrap_or_default()))?;
+        let server_changed = ProfileUpdate {
+            server_url: Some(server_url),
+            client_token: Some(client_token),
+            tls,
+            proxy,
+            ..ProfileUpdate::default()
+        }
+        .apply(profile);
+        let profile = profile.clone();
 
-    // 保存到文件
-    let config_dir = get_config_dir();
-    let config_path = format!("{}/.gotify_config.json", config_dir);
+        persist_config(&app_config)?;
+        (profile, server_changed)
+    };
 
-    match std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap()) {
-        Ok(_) => {
-            println!("✅ 配置已保存到文件: {}", config_path);
-            Ok("Configuration saved successfully".to_string())
-        },
-        Err(e) => {
-            eprintln!("❌ 保存配置失败: {}", e);
-            Err(format!("Failed to save config: {}", e))
-        }
+    if server_changed {
+        reconcile_profile(&app, &state, &profile, true).await;
     }
+    Ok("Configuration saved successfully".to_string())
 }
 
+/// 校验服务器地址，成功时返回规范化后的地址
 #[tauri::command]
-async fn test_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+async fn validate_server_url(server_url: String) -> Result<String, AppError> {
+    Ok(ServerUrl::parse(&server_url)?.normalized())
+}
+
+#[tauri::command]
+async fn test_settings_window(app: tauri::AppHandle) -> Result<(), AppError> {
     println!("🧪 测试调用设置窗口...");
     show_settings_window(app).await
 }
 
 #[tauri::command]
-async fn get_system_theme() -> Result<String, String> {
+async fn get_system_theme() -> Result<String, AppError> {
     println!("🎨 获取系统主题...");
 
     #[cfg(target_os = "macos")]
@@ -164,7 +356,7 @@ async fn get_system_theme() -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn show_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+async fn show_settings_window(app: tauri::AppHandle) -> Result<(), AppError> {
     // 检查是否已有设置窗口
     if let Some(window) = app.get_webview_window("settings") {
         let _ = window.show();
@@ -184,172 +376,752 @@ async fn show_settings_window(app: tauri::AppHandle) -> Result<(), String> {
     .min_inner_size(700.0, 500.0)
     .center()
     .build()
-    .map_err(|e| format!("Failed to create settings window: {}", e))?;
+    .map_err(|e| AppError::Window(format!("创建设置窗口失败: {}", e)))?;
 
     Ok(())
 }
 
-fn load_config_from_file() -> Option<ConnectionConfig> {
-    let config_dir = get_config_dir();
-    let config_path = format!("{}/.gotify_config.json", config_dir);
-
-    match std::fs::read_to_string(&config_path) {
-        Ok(content) => {
-            match serde_json::from_str::<ConnectionConfig>(&content) {
-                Ok(config) => {
-                    println!("✅ 从文件加载配置成功: {}", config_path);
-                    Some(config)
-                },
-                Err(e) => {
-                    eprintln!("❌ 解析配置文件失败: {}", e);
-                    // 如果配置文件解析失败，尝试从环境变量加载
-                    load_config_from_env()
-                }
-            }
-        },
-        Err(_) => {
-            println!("ℹ️ 未找到配置文件，尝试从环境变量加载");
-            load_config_from_env()
-        }
-    }
-}
-
-fn load_config_from_env() -> Option<ConnectionConfig> {
-    let server_url = std::env::var("GOTIFY_SERVER_URL")
-        .or_else(|_| std::env::var("GOTIFY_URL"))
-        .unwrap_or_else(|_| "http://localhost:8080".to_string());
-
-    let client_token = std::env::var("GOTIFY_CLIENT_TOKEN")
-        .or_else(|_| std::env::var("GOTIFY_TOKEN"))
-        .unwrap_or_else(|_| "".to_string());
-
-    // 只有当Token不为空时才返回配置
-    if !client_token.is_empty() {
-        println!("✅ 从环境变量加载配置成功");
-        Some(ConnectionConfig {
-            server_url,
-            client_token,
-        })
-    } else {
-        println!("ℹ️ 未找到有效的配置，请通过设置界面配置");
-        None
-    }
-}
-
+/// 取得服务器的连接配置，没有指定 `profile_id` 时返回第一个服务器
 #[tauri::command]
-async fn get_config(state: State<'_, AppState>) -> Result<Option<ConnectionConfig>, String> {
-    let config = state.config.lock().unwrap();
-    Ok(config.clone())
+async fn get_config(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<Option<ConnectionConfig>, AppError> {
+    let app_config = state.config.lock().unwrap();
+    Ok(app_config.resolve(profile_id.as_deref()).map(|p| p.connection.clone()))
 }
 
 #[tauri::command]
 async fn save_config(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     server_url: String,
     client_token: String,
-) -> Result<String, String> {
-    save_config_to_file(state, server_url, client_token).await
+    tls: Option<TlsOptions>,
+    proxy: Option<ProxyOptions>,
+    profile_id: Option<String>,
+) -> Result<String, AppError> {
+    save_config_to_file(app, state, server_url, client_token, tls, proxy, profile_id).await
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct ConnectionTestResult {
+    message: String,
+    /// 服务器出示的证书指纹（仅 https），用于证书固定
+    certificate: Option<CertificateInfo>,
 }
 
 #[tauri::command]
 async fn test_connection(
+    state: State<'_, AppState>,
     server_url: String,
     client_token: String,
-) -> Result<String, String> {
-    // 构建 WebSocket URL
-    let ws_url = {
-        let server_url = server_url.trim_end_matches('/');
-        let protocol = if server_url.starts_with("https://") {
-            "wss://"
-        } else {
-            "ws://"
-        };
-        let host = server_url
-            .trim_start_matches("https://")
-            .trim_start_matches("http://");
-        format!("{}{}/stream?token={}", protocol, host, client_token)
+    tls: Option<TlsOptions>,
+    proxy: Option<ProxyOptions>,
+    profile_id: Option<String>,
+) -> Result<ConnectionTestResult, AppError> {
+    // 未填写的高级设置沿用正在编辑的服务器配置
+    let mut config = {
+        let app_config = state.config.lock().unwrap();
+        let existing = app_config.resolve(profile_id.as_deref()).map(|p| &p.connection);
+        config_with_credentials(existing, server_url, client_token)
     };
-
-    println!("🧪 测试连接到: {}", ws_url);
-
-    // 尝试解析 URL
-    let url = url::Url::parse(&ws_url)
-        .map_err(|e| format!("URL 解析失败: {}", e))?;
+    if let Some(tls) = tls {
+        config.tls = tls;
+    }
+    if let Some(proxy) = proxy {
+        config.proxy = proxy;
+    }
 
     // 尝试连接 WebSocket
     match tokio::time::timeout(
         std::time::Duration::from_secs(10),
-        connect_async(url)
+        open_stream(&config)
     ).await {
-        Ok(Ok((ws_stream, _))) => {
+        Ok(Ok((ws_stream, certificate))) => {
             println!("✅ WebSocket 连接测试成功");
             drop(ws_stream); // 立即关闭测试连接
-            Ok("连接成功".to_string())
+            Ok(ConnectionTestResult {
+                message: "连接成功".to_string(),
+                certificate,
+            })
+        }
+        Ok(Err(ConnectError::Net(NetError::Tls(TlsError::Handshake(e))))) => {
+            eprintln!("❌ TLS 握手失败: {}", e);
+
+            // 证书不受信任时取得证书指纹，方便用户确认后固定
+            let probe = match build_stream_request(&config) {
+                Ok((url, _)) => net::probe_certificate(&url, &config.proxy).await,
+                Err(_) => None,
+            };
+            Err(AppError::Tls {
+                message: format!("TLS 握手失败: {}", e),
+                certificate: probe,
+            })
         }
         Ok(Err(e)) => {
             eprintln!("❌ WebSocket 连接失败: {}", e);
-            Err(format!("连接失败: {}。请检查服务器地址和 Token 是否正确", e))
+            Err(e.into())
         }
         Err(_) => {
             eprintln!("❌ 连接超时");
-            Err("连接超时，请检查服务器地址是否可访问".to_string())
+            Err(AppError::Timeout)
         }
     }
 }
 
 #[tauri::command]
-async fn start_websocket(
+async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<ServerProfile>, AppError> {
+    Ok(state.config.lock().unwrap().profiles.clone())
+}
+
+#[tauri::command]
+async fn add_profile(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    let config = {
-        let cfg = state.config.lock().unwrap();
-        cfg.clone()
+    name: String,
+    server_url: String,
+    client_token: String,
+    color: Option<String>,
+    enabled: Option<bool>,
+) -> Result<ServerProfile, AppError> {
+    let server_url = ServerUrl::parse(&server_url)?.normalized();
+
+    let profile = {
+        let mut app_config = state.config.lock().unwrap();
+        let name = match name.trim() {
+            "" => server_url.clone(),
+            name => name.to_string(),
+        };
+        let profile = ServerProfile {
+            id: app_config.new_profile_id(),
+            name,
+            color: color.unwrap_or_else(|| app_config.next_color()),
+            enabled: enabled.unwrap_or(true),
+            connection: ConnectionConfig::new(server_url, client_token),
+        };
+        app_config.profiles.push(profile.clone());
+        persist_config(&app_config)?;
+        profile
     };
+    println!("➕ 已添加服务器: {} ({})", profile.name, profile.id);
 
-    let config = config.ok_or("No configuration found. Please configure first.")?;
-    
-    let mut ws_running = state.ws_running.lock().unwrap();
-    if *ws_running {
-        return Err("WebSocket is already running".to_string());
+    // 其他服务器正在连接时，新服务器也立即连接
+    if profile.enabled && connections_active(&state) {
+        start_profile(&app, &state, profile.clone());
     }
-    *ws_running = true;
-    drop(ws_running);
+    Ok(profile)
+}
 
-    let ws_running_clone = state.ws_running.clone();
-    
-    tokio::spawn(async move {
-        if let Err(e) = run_websocket(app, config, ws_running_clone).await {
-            eprintln!("WebSocket error: {}", e);
+#[tauri::command]
+async fn update_profile(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    mut update: ProfileUpdate,
+) -> Result<ServerProfile, AppError> {
+    if let Some(server_url) = update.server_url.take() {
+        update.server_url = Some(ServerUrl::parse(&server_url)?.normalized());
+    }
+
+    let (profile, server_changed) = {
+        let mut app_config = state.config.lock().unwrap();
+        let profile = app_config
+            .profile_mut(&id)
+            .ok_or_else(|| AppError::ProfileNotFound(id.clone()))?;
+        let server_changed = update.apply(profile);
+        let profile = profile.clone();
+        persist_config(&app_config)?;
+        (profile, server_changed)
+    };
+    println!("✏️ 已更新服务器: {} ({})", profile.name, profile.id);
+
+    reconcile_profile(&app, &state, &profile, server_changed).await;
+    Ok(profile)
+}
+
+#[tauri::command]
+async fn remove_profile(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), AppError> {
+    {
+        let mut app_config = state.config.lock().unwrap();
+        let index = app_config
+            .profiles
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| AppError::ProfileNotFound(id.clone()))?;
+        app_config.profiles.remove(index);
+        persist_config(&app_config)?;
+    }
+    println!("➖ 已删除服务器: {}", id);
+
+    stop_profiles(&app, &state, std::slice::from_ref(&id)).await;
+    state.status.remove(&id);
+    state.apps.remove_profile(&id);
+    sync::remove_sync_state(&id);
+    Ok(())
+}
+
+/// 按 `ids` 的顺序重新排列服务器，`ids` 必须恰好包含每个服务器一次
+#[tauri::command]
+async fn reorder_profiles(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+) -> Result<Vec<ServerProfile>, AppError> {
+    let mut app_config = state.config.lock().unwrap();
+
+    let mut requested: Vec<&str> = ids.iter().map(String::as_str).collect();
+    requested.sort_unstable();
+    requested.dedup();
+    let mut existing: Vec<&str> = app_config.profiles.iter().map(|p| p.id.as_str()).collect();
+    existing.sort_unstable();
+    if requested.len() != ids.len() || requested != existing {
+        return Err(AppError::InvalidConfig(
+            "排序列表必须恰好包含每个服务器一次".to_string(),
+        ));
+    }
+
+    app_config
+        .profiles
+        .sort_by_key(|p| ids.iter().position(|id| *id == p.id));
+    persist_config(&app_config)?;
+    Ok(app_config.profiles.clone())
+}
+
+/// 启动连接。指定 `profile_id` 时只连接该服务器，否则连接所有已启用的服务器
+#[tauri::command]
+async fn start_websocket(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<String, AppError> {
+    let profiles: Vec<ServerProfile> = {
+        let app_config = state.config.lock().unwrap();
+        match &profile_id {
+            Some(id) => vec![app_config
+                .profile(id)
+                .cloned()
+                .ok_or_else(|| AppError::ProfileNotFound(id.clone()))?],
+            None => app_config.profiles.iter().filter(|p| p.enabled).cloned().collect(),
         }
-    });
+    };
+    if profiles.is_empty() {
+        return Err(AppError::NotConfigured);
+    }
+
+    let mut started = 0;
+    for profile in profiles {
+        if start_profile(&app, &state, profile) {
+            started += 1;
+        }
+    }
+    if started == 0 {
+        return Err(AppError::AlreadyRunning);
+    }
 
     Ok("WebSocket connection started".to_string())
 }
 
+/// 停止连接。指定 `profile_id` 时只断开该服务器，否则断开全部
 #[tauri::command]
-async fn stop_websocket(state: State<'_, AppState>) -> Result<String, String> {
-    let mut ws_running = state.ws_running.lock().unwrap();
-    *ws_running = false;
+async fn stop_websocket(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<String, AppError> {
+    let ids = match profile_id {
+        Some(id) => vec![id],
+        None => state.ws_tasks.lock().unwrap().keys().cloned().collect(),
+    };
+    stop_profiles(&app, &state, &ids).await;
     Ok("WebSocket connection stopped".to_string())
 }
 
 #[tauri::command]
-async fn is_websocket_running(state: State<'_, AppState>) -> Result<bool, String> {
-    let ws_running = state.ws_running.lock().unwrap();
-    Ok(*ws_running)
+async fn restart_websocket(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<String, AppError> {
+    stop_websocket(app.clone(), state.clone(), profile_id.clone()).await?;
+    start_websocket(app, state, profile_id).await
+}
+
+/// 连接任务是否在运行（包括正在连接和等待重连），准确的连接状态见 `get_connection_status`
+#[tauri::command]
+async fn is_websocket_running(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<bool, AppError> {
+    let ws_tasks = state.ws_tasks.lock().unwrap();
+    Ok(match profile_id {
+        Some(id) => ws_tasks.get(&id).is_some_and(WsTask::is_running),
+        None => ws_tasks.values().any(WsTask::is_running),
+    })
+}
+
+/// 取得服务器的连接状态，没有指定 `profile_id` 时返回第一个服务器
+#[tauri::command]
+async fn get_connection_status(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<ConnectionStatus, AppError> {
+    let id = {
+        let app_config = state.config.lock().unwrap();
+        match profile_id {
+            Some(id) => app_config
+                .profile(&id)
+                .map(|p| p.id.clone())
+                .ok_or(AppError::ProfileNotFound(id))?,
+            None => app_config
+                .profiles
+                .first()
+                .map(|p| p.id.clone())
+                .ok_or(AppError::NotConfigured)?,
+        }
+    };
+    Ok(state.status.snapshot(&id))
+}
+
+/// 分页查询本地保存的历史消息，按时间从新到旧排列
+#[tauri::command]
+async fn get_messages(
+    state: State<'_, AppState>,
+    offset: Option<u32>,
+    limit: Option<u32>,
+    filters: Option<MessageFilter>,
+) -> Result<MessagePage, AppError> {
+    let mut page = message_store(&state)?.query(offset.unwrap_or(0), limit, &filters.unwrap_or_default())?;
+    for message in &mut page.messages {
+        state.apps.annotate(message);
+    }
+    Ok(page)
+}
+
+/// 全文搜索历史消息的标题和正文，按相关度排序并返回匹配位置
+#[tauri::command]
+async fn search_messages(
+    state: State<'_, AppState>,
+    query: String,
+    filters: Option<MessageFilter>,
+    offset: Option<u32>,
+    limit: Option<u32>,
+) -> Result<SearchResults, AppError> {
+    let store = message_store(&state)?;
+    let mut results = store.search(&query, offset.unwrap_or(0), limit, &filters.unwrap_or_default())?;
+    for hit in &mut results.hits {
+        state.apps.annotate(&mut hit.message);
+    }
+    Ok(results)
+}
+
+/// 用服务器配置中的凭据创建 REST 客户端
+fn rest_client(state: &AppState, profile_id: &str) -> Result<rest::GotifyClient, AppError> {
+    let app_config = state.config.lock().unwrap();
+    let profile = app_config
+        .profile(profile_id)
+        .ok_or_else(|| AppError::ProfileNotFound(profile_id.to_string()))?;
+    Ok(rest::GotifyClient::new(&profile.connection)?)
+}
+
+/// 服务器确认删除后推送，`id` 和 `appid` 都为空表示删除了该服务器的所有消息
+#[derive(Clone, Serialize, Debug)]
+struct MessagesDeletedEvent {
+    profile_id: String,
+    id: Option<u64>,
+    appid: Option<u64>,
+}
+
+/// 服务器确认删除后同步本地历史，并通知所有窗口
+fn messages_deleted(app: &tauri::AppHandle, event: MessagesDeletedEvent) -> Result<u64, AppError> {
+    let state = app.state::<AppState>();
+    let deleted = match (&state.store, event.id) {
+        (Some(store), Some(id)) => store.delete(&event.profile_id, id)? as u64,
+        (Some(store), None) => store.delete_all(&event.profile_id, event.appid)?,
+        (None, _) => 0,
+    };
+    app.emit("messages-deleted", &event).ok();
+    publish_unread(app);
+    Ok(deleted)
+}
+
+/// 在服务器上删除一条消息，服务器上已经不存在时同样删除本地记录
+#[tauri::command]
+async fn delete_message(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: String,
+    id: u64,
+) -> Result<(), AppError> {
+    match rest_client(&state, &profile_id)?.delete_message(id).await {
+        Ok(()) | Err(rest::RestError::Status(hyper::StatusCode::NOT_FOUND)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    println!("🗑️ 已在服务器上删除消息: {}", id);
+    messages_deleted(&app, MessagesDeletedEvent { profile_id, id: Some(id), appid: None })?;
+    Ok(())
+}
+
+/// 在服务器上删除某个应用的所有消息，返回本地删除的条数
+#[tauri::command]
+async fn delete_app_messages(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: String,
+    appid: u64,
+) -> Result<u64, AppError> {
+    match rest_client(&state, &profile_id)?
+        .delete_application_messages(appid)
+        .await
+    {
+        Ok(()) | Err(rest::RestError::Status(hyper::StatusCode::NOT_FOUND)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    println!("🗑️ 已在服务器上删除应用 {} 的所有消息", appid);
+    messages_deleted(&app, MessagesDeletedEvent { profile_id, id: None, appid: Some(appid) })
+}
+
+/// 删除一个服务器上全部消息的结果
+#[derive(Serialize, Debug)]
+struct DeleteAllResult {
+    profile_id: String,
+    /// 本地删除的条数
+    deleted: u64,
+    /// 删除失败的原因，成功时为空
+    error: Option<AppError>,
+}
+
+async fn delete_profile_messages(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    profile_id: &str,
+) -> Result<u64, AppError> {
+    rest_client(state, profile_id)?.delete_all_messages().await?;
+    println!("🗑️ 已在服务器 {} 上删除所有消息", profile_id);
+    messages_deleted(app, MessagesDeletedEvent {
+        profile_id: profile_id.to_string(),
+        id: None,
+        appid: None,
+    })
+}
+
+/// 在服务器上删除所有消息，没有指定 `profile_id` 时依次删除每个服务器的消息。
+/// 某个服务器失败时继续处理其他服务器，返回每个服务器的结果
+#[tauri::command]
+async fn delete_all_messages(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<Vec<DeleteAllResult>, AppError> {
+    let profile_ids = match profile_id {
+        Some(id) => vec![id],
+        None => state.config.lock().unwrap().profiles.iter().map(|p| p.id.clone()).collect(),
+    };
+
+    let mut results = Vec::new();
+    for profile_id in profile_ids {
+        let result = delete_profile_messages(&app, &state, &profile_id).await;
+        if let Err(e) = &result {
+            eprintln!("❌ 删除服务器 {} 上的消息失败: {}", profile_id, e);
+        }
+        results.push(DeleteAllResult {
+            profile_id,
+            deleted: *result.as_ref().unwrap_or(&0),
+            error: result.err(),
+        });
+    }
+    Ok(results)
+}
+
+/// 返回缓存的应用列表，`refresh` 为 true 时先从服务器重新获取。
+/// 没有指定 `profile_id` 时返回所有服务器的应用
+#[tauri::command]
+async fn list_applications(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+    refresh: Option<bool>,
+) -> Result<Vec<AppInfo>, AppError> {
+    if refresh.unwrap_or(false) {
+        let profiles: Vec<ServerProfile> = {
+            let app_config = state.config.lock().unwrap();
+            match &profile_id {
+                Some(id) => vec![app_config
+                    .profile(id)
+                    .cloned()
+                    .ok_or_else(|| AppError::ProfileNotFound(id.clone()))?],
+                None => app_config.profiles.clone(),
+            }
+        };
+        for profile in &profiles {
+            state.apps.refresh(profile).await?;
+        }
+    }
+    Ok(state.apps.list(profile_id.as_deref()))
+}
+
+#[tauri::command]
+async fn get_priority_map(state: State<'_, AppState>) -> Result<PriorityMap, AppError> {
+    Ok(state.config.lock().unwrap().priorities.clone())
+}
+
+/// 保存优先级映射，范围无效或重叠时拒绝保存
+#[tauri::command]
+async fn set_priority_map(state: State<'_, AppState>, map: PriorityMap) -> Result<PriorityMap, AppError> {
+    let map = map.validate()?;
+    let mut app_config = state.config.lock().unwrap();
+    app_config.priorities = map.clone();
+    persist_config(&app_config)?;
+    Ok(map)
+}
+
+#[tauri::command]
+async fn get_filter_rules(state: State<'_, AppState>) -> Result<Vec<FilterRule>, AppError> {
+    Ok(state.config.lock().unwrap().filters.clone())
+}
+
+/// 只校验过滤规则，不保存。设置界面在保存其他设置之前先调用，避免只保存了一部分
+#[tauri::command]
+async fn validate_filter_rules(rules: Vec<FilterRule>) -> Result<(), AppError> {
+    FilterSet::compile(&rules)?;
+    Ok(())
+}
+
+/// 校验并保存过滤规则，立即对之后收到的消息生效
+#[tauri::command]
+async fn set_filter_rules(state: State<'_, AppState>, rules: Vec<FilterRule>) -> Result<Vec<FilterRule>, AppError> {
+    let compiled = FilterSet::compile(&rules)?;
+    {
+        let mut app_config = state.config.lock().unwrap();
+        app_config.filters = rules.clone();
+        persist_config(&app_config)?;
+    }
+    *state.filters.lock().unwrap() = compiled;
+    println!("🧹 已更新 {} 条过滤规则", rules.len());
+    Ok(rules)
+}
+
+#[tauri::command]
+async fn list_app_rules(state: State<'_, AppState>) -> Result<Vec<AppRule>, AppError> {
+    Ok(state.config.lock().unwrap().app_rules.clone())
+}
+
+/// 保存应用的通知设置，服务器和应用相同的设置会被替换
+#[tauri::command]
+async fn save_app_rule(state: State<'_, AppState>, rule: AppRule) -> Result<Vec<AppRule>, AppError> {
+    let mut app_config = state.config.lock().unwrap();
+    app_rules::upsert(&mut app_config.app_rules, rule);
+    persist_config(&app_config)?;
+    Ok(app_config.app_rules.clone())
+}
+
+#[tauri::command]
+async fn remove_app_rule(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+    appid: u64,
+) -> Result<Vec<AppRule>, AppError> {
+    let mut app_config = state.config.lock().unwrap();
+    if app_rules::remove(&mut app_config.app_rules, profile_id.as_deref(), appid) {
+        persist_config(&app_config)?;
+    }
+    Ok(app_config.app_rules.clone())
+}
+
+/// 返回应用图标的 data URL，没有缓存图标时返回 null
+#[tauri::command]
+async fn get_app_icon(
+    state: State<'_, AppState>,
+    profile_id: String,
+    appid: u64,
+) -> Result<Option<String>, AppError> {
+    let Some(icon_path) = state.apps.get(&profile_id, appid).and_then(|app| app.icon_path) else {
+        return Ok(None);
+    };
+    Ok(Some(apps::icon_data_url(&icon_path)?))
+}
+
+/// 标记或取消标记保留消息，标记后不会被保留策略清理
+#[tauri::command]
+async fn set_message_keep(
+    state: State<'_, AppState>,
+    profile_id: String,
+    id: u64,
+    keep: bool,
+) -> Result<(), AppError> {
+    if !message_store(&state)?.set_keep(&profile_id, id, keep)? {
+        return Err(AppError::Storage(format!("找不到消息: {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_retention_policy(state: State<'_, AppState>) -> Result<RetentionPolicy, AppError> {
+    Ok(state.config.lock().unwrap().retention.clone())
+}
+
+/// 保存保留策略并立即按新策略清理一次
+#[tauri::command]
+async fn set_retention_policy(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    policy: RetentionPolicy,
+) -> Result<Option<PruneReport>, AppError> {
+    {
+        let mut app_config = state.config.lock().unwrap();
+        app_config.retention = policy;
+        persist_config(&app_config)?;
+    }
+    prune_messages(app).await
+}
+
+/// 立即按保留策略清理一次，返回删除的消息数
+#[tauri::command]
+async fn prune_messages(app: tauri::AppHandle) -> Result<Option<PruneReport>, AppError> {
+    tokio::task::spawn_blocking(move || retention::prune_now(&app))
+        .await
+        .map_err(|e| AppError::Storage(format!("清理历史消息失败: {}", e)))
+}
+
+#[tauri::command]
+async fn get_storage_stats(state: State<'_, AppState>) -> Result<StoreStats, AppError> {
+    Ok(message_store(&state)?.stats()?)
+}
+
+/// 把消息标记为已读，`read` 为 false 时标记为未读
+#[tauri::command]
+async fn mark_read(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: String,
+    ids: Vec<u64>,
+    read: Option<bool>,
+) -> Result<u64, AppError> {
+    let changed = message_store(&state)?.mark_read(&profile_id, &ids, read.unwrap_or(true))?;
+    if changed > 0 {
+        publish_unread(&app);
+    }
+    Ok(changed)
+}
+
+/// 把所有未读消息标记为已读，可以只限于某个服务器或应用
+#[tauri::command]
+async fn mark_all_read(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+    appid: Option<u64>,
+) -> Result<u64, AppError> {
+    let changed = message_store(&state)?.mark_all_read(profile_id.as_deref(), appid)?;
+    if changed > 0 {
+        publish_unread(&app);
+    }
+    Ok(changed)
+}
+
+#[tauri::command]
+async fn get_unread_counts(state: State<'_, AppState>) -> Result<UnreadCounts, AppError> {
+    Ok(message_store(&state)?.unread_counts()?)
+}
+
+/// 未读数变化后更新托盘提示，并推送 `unread-changed` 事件让所有窗口保持一致
+fn publish_unread(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let Some(store) = &state.store else {
+        return;
+    };
+    let counts = match store.unread_counts() {
+        Ok(counts) => counts,
+        Err(e) => {
+            eprintln!("❌ 统计未读消息失败: {}", e);
+            return;
+        }
+    };
+
+    if let Some(tray) = state.tray_icon.lock().unwrap().as_ref() {
+        let tooltip = if counts.total > 0 {
+            format!("Gotify Tray - {} 条未读", counts.total)
+        } else {
+            "Gotify Tray".to_string()
+        };
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+
+    app.emit("unread-changed", &counts).ok();
+}
+
+/// 把满足筛选条件的历史消息导出到 `path`，按时间从旧到新排列
+#[tauri::command]
+async fn export_messages(
+    state: State<'_, AppState>,
+    path: String,
+    format: ExportFormat,
+    filters: Option<MessageFilter>,
+) -> Result<ExportSummary, AppError> {
+    if path.trim().is_empty() {
+        return Err(AppError::InvalidConfig("请选择导出文件的路径".to_string()));
+    }
+    let messages = message_store(&state)?.all_messages(&filters.unwrap_or_default())?;
+
+    let profile_ids = export::profile_ids(&messages);
+    let profiles: Vec<ServerProfile> = {
+        let app_config = state.config.lock().unwrap();
+        app_config
+            .profiles
+            .iter()
+            .filter(|p| profile_ids.contains(&p.id))
+            .cloned()
+            .collect()
+    };
+    let names = ExportNames::collect(&profiles, &state.apps, &messages).await;
+    let messages = export::build(messages, &names);
+
+    let count = messages.len();
+    let target = std::path::PathBuf::from(&path);
+    tokio::task::spawn_blocking(move || export::write(&target, format, &messages))
+        .await
+        .map_err(|e| AppError::Io(format!("导出消息失败: {}", e)))??;
+
+    println!("📤 已导出 {} 条消息到 {}", count, path);
+    Ok(ExportSummary { path, count })
+}
+
+fn message_store(state: &AppState) -> Result<&MessageStore, AppError> {
+    state
+        .store
+        .as_deref()
+        .ok_or_else(|| AppError::Storage("消息数据库不可用".to_string()))
+}
+
+/// 按显示顺序返回所有服务器的连接状态
+#[tauri::command]
+async fn list_connection_statuses(state: State<'_, AppState>) -> Result<Vec<ConnectionStatus>, AppError> {
+    let app_config = state.config.lock().unwrap();
+    Ok(app_config
+        .profiles
+        .iter()
+        .map(|p| state.status.snapshot(&p.id))
+        .collect())
 }
 
 #[tauri::command]
 async fn save_window_position_auto(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let window = app.get_webview_window("main").unwrap();
+) -> Result<(), AppError> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| AppError::Window("找不到主窗口".to_string()))?;
     
     // 直接从窗口获取位置和大小
-    let position = window.outer_position().map_err(|e| format!("获取位置失败: {}", e))?;
-    let size = window.outer_size().map_err(|e| format!("获取大小失败: {}", e))?;
+    let position = window
+        .outer_position()
+        .map_err(|e| AppError::Window(format!("获取位置失败: {}", e)))?;
+    let size = window
+        .outer_size()
+        .map_err(|e| AppError::Window(format!("获取大小失败: {}", e)))?;
     
     let window_pos = WindowPosition {
         x: position.x,
@@ -372,7 +1144,7 @@ async fn save_window_position_auto(
         },
         Err(e) => {
             eprintln!("❌ 保存窗口位置失败: {}", e);
-            Err(format!("保存失败: {}", e))
+            Err(AppError::Io(format!("保存窗口位置失败: {}", e)))
         }
     }
 }
@@ -384,7 +1156,7 @@ async fn save_window_position(
     y: i32,
     width: u32,
     height: u32,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let position = WindowPosition { x, y, width, height };
     let mut window_position = state.window_position.lock().unwrap();
     *window_position = Some(position.clone());
@@ -400,13 +1172,13 @@ async fn save_window_position(
         },
         Err(e) => {
             eprintln!("❌ 保存窗口位置失败: {}", e);
-            Err(format!("保存失败: {}", e))
+            Err(AppError::Io(format!("保存窗口位置失败: {}", e)))
         }
     }
 }
 
 #[tauri::command]
-async fn load_window_position(state: State<'_, AppState>) -> Result<Option<WindowPosition>, String> {
+async fn load_window_position(state: State<'_, AppState>) -> Result<Option<WindowPosition>, AppError> {
     let config_dir = get_config_dir();
     let config_path = format!("{}/.gotify_window_position.json", config_dir);
     
@@ -434,121 +1206,799 @@ async fn load_window_position(state: State<'_, AppState>) -> Result<Option<Windo
 
 
 
+/// 单次连接的结束原因，决定监督循环是否继续重连
+enum SessionEnd {
+    /// 用户主动停止
+    Stopped,
+    /// 连接断开或建立失败，可以重试
+    Lost(AppError),
+    /// 致命错误（如 Token 无效），重试没有意义
+    Fatal(AppError),
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct ReconnectEvent {
+    profile_id: String,
+    attempt: u32,
+    delay_ms: u64,
+    reason: String,
+}
+
+/// `websocket-status` 事件：某个服务器连接或断开
+#[derive(Clone, Serialize, Debug)]
+struct WebSocketStatusEvent {
+    profile_id: String,
+    status: &'static str,
+}
+
+/// `websocket-fatal-error` 事件：某个服务器遇到致命错误，已停止重连
+#[derive(Clone, Serialize, Debug)]
+struct FatalErrorEvent {
+    profile_id: String,
+    error: AppError,
+}
+
+/// 等待连接任务发送关闭帧并退出的最长时间
+const WS_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 启动 WebSocket 监督任务
+fn spawn_websocket(app: tauri::AppHandle, profile: ServerProfile) -> WsTask {
+    let cancel = CancellationToken::new();
+    let task_cancel = cancel.clone();
+
+    let handle = tokio::spawn(async move {
+        if let Err(e) = run_websocket(app, profile, task_cancel).await {
+            eprintln!("WebSocket error: {}", e);
+        }
+    });
+
+    WsTask { cancel, handle }
+}
+
+/// 为服务器启动连接任务，已在运行时返回 false
+fn start_profile(app: &tauri::AppHandle, state: &AppState, profile: ServerProfile) -> bool {
+    let mut ws_tasks = state.ws_tasks.lock().unwrap();
+    if ws_tasks.get(&profile.id).is_some_and(WsTask::is_running) {
+        return false;
+    }
+    println!("🚀 连接服务器: {} ({})", profile.name, profile.id);
+    ws_tasks.insert(profile.id.clone(), spawn_websocket(app.clone(), profile));
+    true
+}
+
+/// 是否有服务器正在连接
+fn connections_active(state: &AppState) -> bool {
+    state.ws_tasks.lock().unwrap().values().any(WsTask::is_running)
+}
+
+/// 取消服务器的连接任务，并等待连接真正关闭
+async fn stop_profiles(app: &tauri::AppHandle, state: &AppState, ids: &[String]) {
+    let tasks: Vec<(String, WsTask)> = {
+        let mut ws_tasks = state.ws_tasks.lock().unwrap();
+        ids.iter()
+            .filter_map(|id| ws_tasks.remove(id).map(|task| (id.clone(), task)))
+            .collect()
+    };
+
+    for (_, task) in &tasks {
+        task.cancel.cancel();
+    }
+
+    // 同时等待所有任务发送关闭帧并退出
+    futures_util::future::join_all(tasks.into_iter().map(|(id, task)| async move {
+        let mut handle = task.handle;
+        if tokio::time::timeout(WS_STOP_TIMEOUT, &mut handle).await.is_err() {
+            eprintln!("⚠️ 连接任务未能及时退出，强制终止: {}", id);
+            handle.abort();
+        }
+        state.status.tracker(&id).transition(app, ConnectionState::Stopped, None);
+        println!("🛑 WebSocket 连接已关闭: {}", id);
+    }))
+    .await;
+}
+
+/// 服务器配置修改后，让它的连接与新配置一致：正在运行的连接用新配置重连，被停用的服务器断开
+async fn reconcile_profile(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    profile: &ServerProfile,
+    server_changed: bool,
+) {
+    let running = state
+        .ws_tasks
+        .lock()
+        .unwrap()
+        .get(&profile.id)
+        .is_some_and(WsTask::is_running);
+    let active = running || connections_active(state);
+
+    if running {
+        stop_profiles(app, state, std::slice::from_ref(&profile.id)).await;
+    }
+    // 旧连接停止后再清除，避免它把旧服务器的进度写回去
+    if server_changed {
+        reset_server_state(app, state, &profile.id);
+    }
+    if active && profile.enabled {
+        start_profile(app, state, profile.clone());
+    }
+}
+
+/// 服务器地址或 Token 变化后清除该服务器的同步进度、应用列表和消息历史，下次连接按首次连接处理。
+///
+/// 本地历史以（服务器配置 id, 消息 id）为主键，新服务器的消息 id 会与旧消息重复而被忽略
+fn reset_server_state(app: &tauri::AppHandle, state: &AppState, profile_id: &str) {
+    println!("🔄 服务器已更换，清除 {} 的同步进度和消息历史", profile_id);
+    sync::remove_sync_state(profile_id);
+    state.apps.remove_profile(profile_id);
+    let event = MessagesDeletedEvent {
+        profile_id: profile_id.to_string(),
+        id: None,
+        appid: None,
+    };
+    if let Err(e) = messages_deleted(app, event) {
+        eprintln!("❌ 清除旧服务器的消息历史失败: {}", e);
+    }
+}
+
+/// WebSocket 监督循环：连接断开后按退避策略自动重连
 async fn run_websocket(
     app: tauri::AppHandle,
-    config: ConnectionConfig,
-    ws_running: Arc<Mutex<bool>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // 构建 WebSocket URL
-    let ws_url = {
-        let server_url = config.server_url.trim_end_matches('/');
-        let protocol = if server_url.starts_with("https://") {
-            "wss://"
-        } else {
-            "ws://"
+    profile: ServerProfile,
+    cancel: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let policy = profile.connection.reconnect.clone();
+    let status = app.state::<AppState>().status.tracker(&profile.id);
+    let mut attempt: u32 = 0;
+
+    let result = loop {
+        status.transition(&app, ConnectionState::Connecting { attempt }, None);
+        let (end, connected) = connect_once(&app, &profile, &status, &cancel).await;
+
+        // 成功建立过连接后重新计数
+        if connected {
+            attempt = 0;
+        }
+
+        let error = match end {
+            SessionEnd::Stopped => break Ok(()),
+            SessionEnd::Fatal(error) => {
+                eprintln!("❌ 致命错误，停止重连: {}", error);
+                let state = if matches!(error, AppError::Unauthorized(_)) {
+                    ConnectionState::AuthFailed
+                } else {
+                    ConnectionState::Unreachable
+                };
+                status.transition(&app, state, Some(error.clone()));
+                app.emit("websocket-fatal-error", FatalErrorEvent {
+                    profile_id: profile.id.clone(),
+                    error: error.clone(),
+                }).ok();
+                break Err(error.into());
+            }
+            SessionEnd::Lost(error) => error,
         };
-        let host = server_url
-            .trim_start_matches("https://")
-            .trim_start_matches("http://");
-        format!("{}{}/stream?token={}", protocol, host, config.client_token)
+
+        attempt += 1;
+        if policy.exhausted(attempt) {
+            eprintln!("❌ 已达到最大重连次数，停止重连: {}", error);
+            status.transition(&app, ConnectionState::Unreachable, Some(error.clone()));
+            break Err(error.into());
+        }
+
+        let delay = policy.delay_for(attempt);
+        println!("🔄 {} 毫秒后进行第 {} 次重连 ({})", delay.as_millis(), attempt, error);
+        status.backoff(&app, attempt, delay, error.clone());
+        app.emit("websocket-reconnecting", ReconnectEvent {
+            profile_id: profile.id.clone(),
+            attempt,
+            delay_ms: delay.as_millis() as u64,
+            reason: error.to_string(),
+        }).ok();
+
+        tokio::select! {
+            _ = cancel.cancelled() => break Ok(()),
+            _ = tokio::time::sleep(delay) => {}
+        }
     };
 
-    println!("Connecting to: {}", ws_url);
+    if cancel.is_cancelled() {
+        status.transition(&app, ConnectionState::Stopped, None);
+    }
 
-    let url = url::Url::parse(&ws_url)?;
-    let (ws_stream, _) = connect_async(url).await?;
-    println!("WebSocket connected successfully");
+    result
+}
 
-    let (_write, mut read) = ws_stream.split();
+/// 建立一次 WebSocket 连接并读取消息，直到断开。第二个返回值表示是否连接成功过
+async fn connect_once(
+    app: &tauri::AppHandle,
+    profile: &ServerProfile,
+    status: &StatusTracker,
+    cancel: &CancellationToken,
+) -> (SessionEnd, bool) {
+    let config = &profile.connection;
+    let connected = tokio::select! {
+        _ = cancel.cancelled() => return (SessionEnd::Stopped, false),
+        connected = open_stream(config) => connected,
+    };
+    let ws_stream = match connected {
+        Ok((ws_stream, _)) => ws_stream,
+        Err(e) => return (classify_connect_error(e), false),
+    };
+    println!("WebSocket connected successfully");
+    status.transition(app, ConnectionState::Connected, None);
+
+    let (mut write, mut read) = ws_stream.split();
+
+    // 心跳：定期发送 Ping，超过期限没有收到任何数据则认为连接已失效
+    let heartbeat_enabled = config.ping_interval_secs > 0;
+    let ping_interval = std::time::Duration::from_secs(config.ping_interval_secs.max(1));
+    let pong_timeout = std::time::Duration::from_secs(config.pong_timeout_secs.max(1));
+    let mut ping_timer = tokio::time::interval_at(
+        tokio::time::Instant::now() + ping_interval,
+        ping_interval,
+    );
+    ping_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut pong_deadline: Option<tokio::time::Instant> = None;
+
+    // 补拉断线期间错过的消息
+    let previous = sync::load_sync_state(&profile.id).last_message_id;
+    let mut last_seen = tokio::select! {
+        _ = cancel.cancelled() => previous,
+        last_seen = catch_up(app, profile, previous) => last_seen,
+    };
+    if last_seen != previous {
+        sync::save_sync_state(&profile.id, &sync::SyncState { last_message_id: last_seen });
+    }
 
     // 发送连接成功消息到前端
     println!("📤 发送连接状态到前端...");
-    match app.emit("websocket-status", "connected") {
+    match app.emit("websocket-status", WebSocketStatusEvent {
+        profile_id: profile.id.clone(),
+        status: "connected",
+    }) {
         Ok(_) => println!("✅ websocket-status 已推送"),
         Err(e) => eprintln!("❌ websocket-status 推送失败: {}", e),
     }
 
-    while *ws_running.lock().unwrap() {
-        tokio::select! {
-            msg = read.next() => {
-                match msg {
-                    Some(Ok(Message::Text(text))) => {
-                        println!("Received message: {}", text);
-                        
-                        // 解析 Gotify 消息
-                        if let Ok(gotify_msg) = serde_json::from_str::<GotifyMessage>(&text) {
-                            println!("📨 收到 Gotify 消息:");
-                            println!("   标题: {}", gotify_msg.title);
-                            println!("   内容: {}", gotify_msg.message);
-                            println!("   优先级: {}", gotify_msg.priority);
-                            
-                            // 发送 macOS 系统通知
-                            use tauri_plugin_notification::NotificationExt;
-                            
-                            println!("🔔 准备发送系统通知...");
-                            println!("   标题: {}", gotify_msg.title);
-                            println!("   优先级: {}", gotify_msg.priority);
-                            
-                            // 构建通知
-                            let mut notification = app.notification().builder();
-                            notification = notification
-                                .title(&gotify_msg.title)
-                                .body(&gotify_msg.message);
-                            
-                            // 高优先级消息添加声音
-                            if gotify_msg.priority >= 5 {
-                                println!("   添加默认系统声音");
-                                notification = notification.sound("default");
-                            }
-                            
-                            match notification.show() {
-                                Ok(_) => println!("✅ macOS 系统通知已成功发送"),
-                                Err(e) => eprintln!("❌ 发送通知失败: {:?}", e),
-                            }
-                            
-                            // 发送消息到前端
-                            println!("📤 发送消息到前端...");
-                            
-                            // 发送到前端
-                            match app.emit("gotify-message", &gotify_msg) {
-                                Ok(_) => println!("✅ 消息已推送到前端"),
-                                Err(e) => eprintln!("❌ 推送失败: {}", e),
+    let end = loop {
+        let msg = tokio::select! {
+            _ = cancel.cancelled() => {
+                // 发送关闭帧，并等待服务器确认后再断开
+                let _ = tokio::time::timeout(WS_STOP_TIMEOUT, async {
+                    let frame = CloseFrame {
+                        code: CloseCode::Normal,
+                        reason: "client stopped".into(),
+                    };
+                    if write.send(Message::Close(Some(frame))).await.is_ok() {
+                        while let Some(Ok(msg)) = read.next().await {
+                            if msg.is_close() {
+                                break;
                             }
                         }
                     }
-                    Some(Ok(Message::Close(_))) => {
-                        println!("WebSocket closed");
-                        break;
-                    }
-                    Some(Err(e)) => {
-                        eprintln!("WebSocket error: {}", e);
-                        break;
-                    }
-                    None => {
-                        println!("WebSocket stream ended");
-                        break;
+                }).await;
+                break SessionEnd::Stopped;
+            }
+            _ = ping_timer.tick(), if heartbeat_enabled => {
+                if let Err(e) = write.send(Message::Ping(Vec::new())).await {
+                    eprintln!("❌ 发送心跳失败: {}", e);
+                    break SessionEnd::Lost(AppError::Network(format!("发送心跳失败: {}", e)));
+                }
+                if pong_deadline.is_none() {
+                    pong_deadline = Some(tokio::time::Instant::now() + pong_timeout);
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(pong_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if pong_deadline.is_some() =>
+            {
+                eprintln!("💔 {} 秒内未收到心跳响应，连接已失效", pong_timeout.as_secs());
+                break SessionEnd::Lost(AppError::Network("心跳超时".to_string()));
+            }
+            msg = read.next() => msg,
+        };
+
+        // 收到任何数据都说明连接仍然存活
+        if matches!(msg, Some(Ok(_))) {
+            pong_deadline = None;
+        }
+
+        match msg {
+            Some(Ok(Message::Text(text))) => {
+                println!("Received message: {}", text);
+
+                // 解析 Gotify 消息
+                if let Ok(mut gotify_msg) = serde_json::from_str::<GotifyMessage>(&text) {
+                    gotify_msg.profile_id = profile.id.clone();
+
+                    // 补拉时已经处理过的消息不再重复通知
+                    if last_seen.is_some_and(|id| gotify_msg.id <= id) {
+                        println!("⏭️ 跳过已处理的消息: {}", gotify_msg.id);
+                        continue;
                     }
-                    _ => {}
+
+                    annotate_message(app, profile, &mut gotify_msg);
+
+                    deliver_message(app, &gotify_msg, true);
+                    status.record_message();
+
+                    last_seen = Some(gotify_msg.id);
+                    sync::save_sync_state(&profile.id, &sync::SyncState { last_message_id: last_seen });
                 }
             }
+            Some(Ok(Message::Close(frame))) => {
+                println!("WebSocket closed");
+                break SessionEnd::Lost(AppError::Network(match frame {
+                    Some(frame) => format!("服务器关闭连接: {} {}", frame.code, frame.reason),
+                    None => "服务器关闭连接".to_string(),
+                }));
+            }
+            Some(Err(e)) => {
+                eprintln!("WebSocket error: {}", e);
+                break SessionEnd::Lost(AppError::Network(e.to_string()));
+            }
+            None => {
+                println!("WebSocket stream ended");
+                break SessionEnd::Lost(AppError::Network("连接已结束".to_string()));
+            }
+            _ => {}
         }
+    };
+
+    app.emit("websocket-status", WebSocketStatusEvent {
+        profile_id: profile.id.clone(),
+        status: "disconnected",
+    }).ok();
+
+    (end, true)
+}
+
+/// 用缓存补全消息的应用名称和图标。
+///
+/// 遇到未知应用时不等待，在后台刷新应用目录，找到后推送 `app-resolved` 事件由前端补全
+fn annotate_message(app: &tauri::AppHandle, profile: &ServerProfile, message: &mut GotifyMessage) {
+    let apps = app.state::<AppState>().apps.clone();
+    if apps.get(&profile.id, message.appid).is_some() {
+        apps.annotate(message);
+        return;
     }
 
-    app.emit("websocket-status", "disconnected").ok();
-    let mut running = ws_running.lock().unwrap();
-    *running = false;
+    let app = app.clone();
+    let profile = profile.clone();
+    let appid = message.appid;
+    tauri::async_runtime::spawn(async move {
+        if let Some(info) = apps.resolve(&profile, appid).await {
+            app.emit("app-resolved", info).ok();
+        }
+    });
+}
 
-    Ok(())
+/// 发送系统通知并把消息推送到前端
+fn deliver_message(app: &tauri::AppHandle, gotify_msg: &GotifyMessage, notify: bool) {
+    println!("📨 收到 Gotify 消息:");
+    println!("   标题: {}", gotify_msg.title);
+    println!("   内容: {}", gotify_msg.message);
+    println!("   优先级: {}", gotify_msg.priority);
+
+    // 过滤规则可能修改优先级、添加标签和高亮，之后的设置都按修改后的消息处理
+    let mut filtered = gotify_msg.clone();
+    let outcome = app.state::<AppState>().filters.lock().unwrap().apply(&mut filtered);
+    let gotify_msg = &filtered;
+
+    let (rule, mut behavior) = {
+        let state = app.state::<AppState>();
+        let app_config = state.config.lock().unwrap();
+        let rule = app_rules::find(&app_config.app_rules, &gotify_msg.profile_id, gotify_msg.appid).cloned();
+        let mut behavior = app_config.priorities.behavior(gotify_msg.priority);
+        if let Some(rule) = &rule {
+            rule.apply(gotify_msg.priority, &mut behavior);
+        }
+        (rule, behavior)
+    };
+    if outcome.suppress {
+        behavior.silent = true;
+        behavior.bring_to_front = false;
+    }
+    if let Some(sound) = outcome.sound {
+        behavior.sound = Some(sound);
+    }
+    let click = rule.as_ref().map(|rule| rule.click).unwrap_or_default();
+
+    // 只保存的应用，消息直接记为已读
+    let store_only = rule.as_ref().is_some_and(|rule| rule.store_only);
+    let gotify_msg = &GotifyMessage {
+        read: gotify_msg.read || store_only,
+        ..gotify_msg.clone()
+    };
+
+    // 先写入本地历史，窗口重新打开时可以从数据库恢复
+    if let Some(store) = &app.state::<AppState>().store {
+        match store.insert(gotify_msg) {
+            Ok(true) => publish_unread(app),
+            Ok(false) => {}
+            Err(e) => eprintln!("❌ 保存消息失败: {}", e),
+        }
+    }
+
+    if notify && behavior.silent {
+        println!("🔕 按优先级、应用设置或过滤规则静默保存，不发送系统通知");
+    } else if notify {
+        println!("🔔 准备发送系统通知...");
+
+        let title = match &gotify_msg.app_name {
+            Some(app_name) => format!("{}: {}", app_name, gotify_msg.title),
+            None => gotify_msg.title.clone(),
+        };
+        let extras = gotify_msg.extras.clone().unwrap_or_default();
+        // Markdown 消息在系统通知中显示为纯文本
+        let body = if extras.is_markdown() {
+            extras::markdown_to_plain(&gotify_msg.message)
+        } else {
+            gotify_msg.message.clone()
+        };
+
+        if let Some(sound) = &behavior.sound {
+            println!("   提示音: {}", sound);
+        }
+
+        notifier::show(app, notifier::Notice {
+            title,
+            body,
+            icon: gotify_msg.app_icon.clone(),
+            sound: behavior.sound.clone(),
+            critical: behavior.critical,
+            click,
+            click_url: extras.click_url(),
+            image: extras.big_image_url(),
+            profile_id: gotify_msg.profile_id.clone(),
+            message_id: gotify_msg.id,
+        });
+    }
+
+    // 发送到前端
+    match app.emit("gotify-message", gotify_msg) {
+        Ok(_) => println!("✅ 消息已推送到前端"),
+        Err(e) => eprintln!("❌ 推送失败: {}", e),
+    }
+
+    // 前端收到消息之后再定位，避免它重新加载历史
+    if notify && behavior.bring_to_front {
+        notifier::focus_message(app, gotify_msg.profile_id.clone(), gotify_msg.id);
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+struct CatchUpEvent {
+    profile_id: String,
+    count: usize,
+    truncated: bool,
+}
+
+/// 首次导入历史消息的进度，每导入一页推送一次，结束时 `done` 为 true
+#[derive(Clone, Serialize, Debug)]
+struct ImportProgressEvent {
+    profile_id: String,
+    imported: u64,
+    /// 本次最多导入的消息数
+    limit: usize,
+    done: bool,
+    /// 导入中途失败的原因，已导入的消息会保留
+    error: Option<AppError>,
+}
+
+/// 从新到旧分页拉取服务器上的消息写入本地历史，直到达到数量或时间限制。
+/// 返回服务器上最新的消息 id，导入失败时返回 None，下次连接会重试
+async fn import_history(
+    app: &tauri::AppHandle,
+    profile: &ServerProfile,
+    client: &rest::GotifyClient,
+) -> Option<u64> {
+    let policy = &profile.connection.import;
+    let cutoff = policy
+        .max_age_days
+        .map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64));
+    let store = app.state::<AppState>().store.clone();
+
+    let progress = |imported: u64, done: bool, error: Option<AppError>| {
+        app.emit("history-import", ImportProgressEvent {
+            profile_id: profile.id.clone(),
+            imported,
+            limit: policy.max_messages,
+            done,
+            error,
+        }).ok();
+    };
+
+    println!("📥 {}: 首次连接，导入服务器上的历史消息...", profile.name);
+    let mut latest = None;
+    let mut fetched = 0;
+    let mut imported = 0;
+    let mut since = 0;
+    loop {
+        let page = match client.messages(rest::PAGE_LIMIT, since).await {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("❌ 导入历史消息失败: {}", e);
+                progress(imported, true, Some(e.into()));
+                // 已经导入过消息时从最新 id 继续，避免下次重复导入
+                return latest;
+            }
+        };
+        latest = latest.or(Some(page.messages.first().map(|m| m.id).unwrap_or(0)));
+
+        let mut batch = Vec::new();
+        let mut reached_limit = false;
+        // 与实时消息一样执行过滤规则，导入的消息也带上优先级修改、标签和高亮
+        let filters = app.state::<AppState>().filters.clone();
+        let filters = filters.lock().unwrap();
+        for mut message in page.messages {
+            let too_old = cutoff.is_some_and(|cutoff| {
+                chrono::DateTime::parse_from_rfc3339(&message.date).is_ok_and(|date| date < cutoff)
+            });
+            if too_old || fetched >= policy.max_messages {
+                reached_limit = true;
+                break;
+            }
+            message.profile_id = profile.id.clone();
+            filters.apply(&mut message);
+            batch.push(message);
+            fetched += 1;
+        }
+        drop(filters);
+
+        if let Some(store) = &store {
+            match store.import(&batch) {
+                Ok(count) => imported += count,
+                Err(e) => {
+                    eprintln!("❌ 保存历史消息失败: {}", e);
+                    progress(imported, true, Some(e.into()));
+                    return latest;
+                }
+            }
+        }
+
+        if reached_limit || page.paging.next.is_none() || batch.is_empty() {
+            break;
+        }
+        progress(imported, false, None);
+        since = page.paging.since;
+    }
+
+    println!("📥 {}: 已导入 {} 条历史消息", profile.name, imported);
+    progress(imported, true, None);
+    latest
+}
+
+/// 通过 REST 接口补拉 id 大于 `last_seen` 的消息，返回补拉后的最大消息 id
+async fn catch_up(
+    app: &tauri::AppHandle,
+    profile: &ServerProfile,
+    last_seen: Option<u64>,
+) -> Option<u64> {
+    let config = &profile.connection;
+    let client = match rest::GotifyClient::new(config) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("❌ 创建 REST 客户端失败: {}", e);
+            return last_seen;
+        }
+    };
+
+    let Some(after_id) = last_seen else {
+        // 首次连接：导入服务器上已有的消息作为历史，不补发通知
+        if config.import.enabled {
+            return import_history(app, profile, &client).await;
+        }
+        return match client.latest_message_id().await {
+            Ok(latest) => Some(latest.unwrap_or(0)),
+            Err(e) => {
+                eprintln!("❌ 获取最新消息 id 失败: {}", e);
+                None
+            }
+        };
+    };
+
+    if !config.catch_up.enabled {
+        return last_seen;
+    }
+
+    let (mut missed, truncated) = match client.messages_after(after_id, config.catch_up.max_messages).await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("❌ 补拉消息失败: {}", e);
+            return last_seen;
+        }
+    };
+
+    if missed.is_empty() {
+        return last_seen;
+    }
+
+    println!("📥 {}: 补拉到 {} 条断线期间的消息", profile.name, missed.len());
+    for message in &mut missed {
+        message.profile_id = profile.id.clone();
+        annotate_message(app, profile, message);
+    }
+
+    // 消息较多时只发一条汇总通知，避免刷屏
+    let summarize = missed.len() > config.catch_up.summary_threshold;
+    for message in &missed {
+        deliver_message(app, message, !summarize);
+    }
+
+    if summarize {
+        use tauri_plugin_notification::NotificationExt;
+
+        let count = if truncated {
+            format!("超过 {} 条", missed.len())
+        } else {
+            format!("{} 条", missed.len())
+        };
+        let titles: Vec<&str> = missed.iter().rev().take(3).map(|m| m.title.as_str()).collect();
+
+        if let Err(e) = app.notification().builder()
+            .title(format!("{}：断线期间收到 {}消息", profile.name, count))
+            .body(titles.join("\n"))
+            .show()
+        {
+            eprintln!("❌ 发送汇总通知失败: {:?}", e);
+        }
+    }
+
+    app.emit("gotify-catch-up", CatchUpEvent {
+        profile_id: profile.id.clone(),
+        count: missed.len(),
+        truncated,
+    }).ok();
+
+    missed.last().map(|m| m.id).or(last_seen)
+}
+
+/// 建立 WebSocket 连接失败的原因
+#[derive(Debug)]
+enum ConnectError {
+    /// 配置有误，无法构建握手请求
+    Request(String),
+    /// TCP 或 TLS 层失败
+    Net(NetError),
+    /// WebSocket 握手失败
+    Handshake(tokio_tungstenite::tungstenite::Error),
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::Request(e) => write!(f, "{}", e),
+            ConnectError::Net(e) => write!(f, "{}", e),
+            ConnectError::Handshake(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+type WsStream = WebSocketStream<net::NetStream>;
+
+/// 建立到 `/stream` 的 WebSocket 连接，同时返回服务器证书指纹
+async fn open_stream(
+    config: &ConnectionConfig,
+) -> Result<(WsStream, Option<CertificateInfo>), ConnectError> {
+    let (url, request) = build_stream_request(config).map_err(ConnectError::Request)?;
+    println!("Connecting to: {}", url);
+
+    let (stream, certificate) = net::connect_inspect(&url, &config.tls, &config.proxy)
+        .await
+        .map_err(ConnectError::Net)?;
+    let (ws_stream, _) = tokio_tungstenite::client_async(request, stream)
+        .await
+        .map_err(ConnectError::Handshake)?;
+
+    Ok((ws_stream, certificate))
+}
+
+/// 构建 WebSocket 握手请求，同时返回不含 Token、可以打印到日志的地址
+fn build_stream_request(config: &ConnectionConfig) -> Result<(url::Url, Request), String> {
+    let display_url = ServerUrl::parse(&config.server_url)
+        .and_then(|server| server.stream_url())
+        .map_err(|e| e.to_string())?;
+    let mut url = display_url.clone();
+
+    if config.token_transport == TokenTransport::Query {
+        url.query_pairs_mut().append_pair("token", &config.client_token);
+    }
+
+    let mut request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| format!("无效的请求: {}", e))?;
+
+    if config.token_transport == TokenTransport::Header {
+        let token = HeaderValue::from_str(&config.client_token)
+            .map_err(|_| "Client Token 包含非法字符".to_string())?;
+        request.headers_mut().insert("X-Gotify-Key", token);
+    }
+
+    Ok((display_url, request))
+}
+
+impl From<ConnectError> for AppError {
+    fn from(e: ConnectError) -> Self {
+        use tokio_tungstenite::tungstenite::Error;
+
+        match e {
+            ConnectError::Request(e) => AppError::InvalidConfig(e),
+            ConnectError::Net(e) => e.into(),
+            ConnectError::Handshake(Error::Http(response)) => {
+                let status = response.status();
+                if status.as_u16() == 401 || status.as_u16() == 403 {
+                    return AppError::Unauthorized(format!("认证失败 ({})，请检查 Client Token", status));
+                }
+                // 反向代理（如 nginx 的 mTLS 校验）通常在响应正文中说明拒绝原因
+                let body = response
+                    .body()
+                    .as_deref()
+                    .map(|body| String::from_utf8_lossy(body).trim().chars().take(200).collect::<String>())
+                    .filter(|body| !body.is_empty());
+                match body {
+                    Some(body) => AppError::Server(format!("服务器返回 {}: {}", status, body)),
+                    None => AppError::Server(format!("服务器返回 {}", status)),
+                }
+            }
+            ConnectError::Handshake(Error::Url(e)) => {
+                AppError::InvalidConfig(format!("无效的服务器地址: {}", e))
+            }
+            ConnectError::Handshake(e) => AppError::Network(e.to_string()),
+        }
+    }
+}
+
+/// 区分连接失败的原因：认证失败、无效地址和证书配置错误不再重试
+fn classify_connect_error(e: ConnectError) -> SessionEnd {
+    use tokio_tungstenite::tungstenite::Error;
+
+    let fatal = match &e {
+        ConnectError::Request(_) => true,
+        ConnectError::Net(e) => e.is_fatal(),
+        ConnectError::Handshake(Error::Http(response)) => {
+            matches!(response.status().as_u16(), 401 | 403)
+        }
+        ConnectError::Handshake(Error::Url(_)) => true,
+        ConnectError::Handshake(_) => false,
+    };
+
+    let error = AppError::from(e);
+    if fatal {
+        SessionEnd::Fatal(error)
+    } else {
+        SessionEnd::Lost(error)
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let config = profiles::load_config();
+    // 配置文件被手动改坏时不阻止启动，只是不执行过滤规则
+    let filters = FilterSet::compile(&config.filters).unwrap_or_else(|e| {
+        eprintln!("❌ 过滤规则无效，已停用: {}", e);
+        FilterSet::default()
+    });
+
     tauri::Builder::default()
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_opener::init())
         .manage(AppState {
-            config: Arc::new(Mutex::new(load_config_from_file())),
-            ws_running: Arc::new(Mutex::new(false)),
+            config: Arc::new(Mutex::new(config)),
+            ws_tasks: Arc::new(Mutex::new(HashMap::new())),
+            status: Arc::new(StatusRegistry::default()),
+            apps: Arc::new(AppDirectory::load()),
+            filters: Arc::new(Mutex::new(filters)),
+            store: match MessageStore::open_default() {
+                Ok(store) => Some(Arc::new(store)),
+                Err(e) => {
+                    eprintln!("❌ {}，消息历史将不会保存", e);
+                    None
+                }
+            },
             window_position: Arc::new(Mutex::new(None)),
             tray_icon: Arc::new(Mutex::new(None)),
         })
@@ -596,36 +2046,30 @@ pub fn run() {
                 // 等待 1 秒后自动连接
                 tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
 
-                let config = {
-                    let cfg = state.config.lock().unwrap();
-                    cfg.clone()
+                let profiles: Vec<ServerProfile> = {
+                    let app_config = state.config.lock().unwrap();
+                    app_config.profiles.iter().filter(|p| p.enabled).cloned().collect()
                 };
 
-                if let Some(config) = config {
-                    println!("🚀 自动连接到 Gotify 服务器...");
-
-                    let mut ws_running = state.ws_running.lock().unwrap();
-                    if !*ws_running {
-                        *ws_running = true;
-                        drop(ws_running);
-
-                        let ws_running_clone = state.ws_running.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = run_websocket(app_handle, config, ws_running_clone).await {
-                                eprintln!("WebSocket error: {}", e);
-                            }
-                        });
-                    }
+                if !profiles.is_empty() {
+                    println!("🚀 自动连接到 {} 个 Gotify 服务器...", profiles.len());
+                }
+                for profile in profiles {
+                    start_profile(&app_handle, &state, profile);
                 }
             });
 
+            // 按保留策略定期清理消息历史
+            retention::spawn_pruner(app.handle().clone());
+
             // 创建系统托盘
             use tauri::{menu::{Menu, MenuItem}};
 
             println!("🔧 开始创建系统托盘...");
             let settings_item = MenuItem::with_id(app, "settings", "设置", true, None::<&str>)?;
+            let delete_all_item = MenuItem::with_id(app, "delete_all", "删除服务器上的全部消息…", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            let tray_menu = Menu::with_items(app, &[&settings_item, &quit_item])?;
+            let tray_menu = Menu::with_items(app, &[&settings_item, &delete_all_item, &quit_item])?;
 
             // 加载托盘图标 - 解码 PNG 以获得 RGBA 数据
             let icon_png = include_bytes!("../icons/icon.png");
@@ -646,6 +2090,14 @@ pub fn run() {
                             println!("🖱️ 用户点击了设置菜单");
                             let _ = app.emit("show-settings", ());
                         }
+                        "delete_all" => {
+                            // 删除无法撤销，由主窗口弹出确认后再调用 delete_all_messages
+                            if let Some(window) = app.get_webview_window("main") {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                            }
+                            let _ = app.emit("confirm-delete-all", ());
+                        }
                         "quit" => {
                             println!("🚪 用户点击了退出菜单");
                             app.exit(0);
@@ -658,13 +2110,13 @@ pub fn run() {
 
             // 保存tray到状态
             let state: tauri::State<AppState> = app.state();
-            let mut tray_icon = state.tray_icon.lock().unwrap();
-            *tray_icon = Some(tray);
+            *state.tray_icon.lock().unwrap() = Some(tray);
+            publish_unread(app.handle());
 
             Ok(())
         })
-        .on_window_event(|window, event| match event {
-            tauri::WindowEvent::ThemeChanged(theme) => {
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::ThemeChanged(theme) = event {
                 println!("🎨 系统主题已更改: {:?}", theme);
 
                 // 发送主题变化事件到前端
@@ -678,16 +2130,48 @@ pub fn run() {
                     "theme": theme_str
                 }));
             }
-            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             save_config,
             save_config_to_file,
             get_config,
+            validate_server_url,
             test_connection,
             start_websocket,
             stop_websocket,
+            restart_websocket,
             is_websocket_running,
+            get_connection_status,
+            list_connection_statuses,
+            get_messages,
+            search_messages,
+            list_applications,
+            get_app_icon,
+            get_priority_map,
+            set_priority_map,
+            get_filter_rules,
+            validate_filter_rules,
+            set_filter_rules,
+            list_app_rules,
+            save_app_rule,
+            remove_app_rule,
+            delete_message,
+            delete_app_messages,
+            delete_all_messages,
+            set_message_keep,
+            get_retention_policy,
+            set_retention_policy,
+            prune_messages,
+            get_storage_stats,
+            mark_read,
+            mark_all_read,
+            get_unread_counts,
+            export_messages,
+            list_profiles,
+            add_profile,
+            update_profile,
+            remove_profile,
+            reorder_profiles,
             save_window_position,
             save_window_position_auto,
             load_window_position,
@@ -0,0 +1,107 @@
+use crate::proxy::{self, ProxyError, ProxyOptions};
+use crate::tls::{self, CertificateInfo, TlsError, TlsOptions};
+use std::fmt;
+use tokio::net::TcpStream;
+use tokio_tungstenite::MaybeTlsStream;
+use url::{Host, Url};
+
+/// 与服务器之间的底层连接（明文或 TLS），WebSocket 和 REST 共用
+pub type NetStream = MaybeTlsStream<TcpStream>;
+
+#[derive(Debug)]
+pub enum NetError {
+    InvalidUrl(String),
+    Connect(std::io::Error),
+    Proxy(ProxyError),
+    Tls(TlsError),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::InvalidUrl(e) => write!(f, "无效的地址: {}", e),
+            NetError::Connect(e) => write!(f, "无法连接服务器: {}", e),
+            NetError::Proxy(e) => write!(f, "{}", e),
+            NetError::Tls(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl NetError {
+    /// 配置错误（地址、代理、证书）重试也不会成功
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            NetError::InvalidUrl(_) => true,
+            NetError::Connect(_) => false,
+            NetError::Proxy(e) => e.is_fatal(),
+            NetError::Tls(e) => e.is_fatal(),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+/// 地址中的主机名，IPv6 地址不带方括号（用于 TLS 的 SNI 和证书校验）
+fn tls_domain(url: &Url) -> Result<String, NetError> {
+    match url.host() {
+        Some(Host::Domain(domain)) => Ok(domain.to_string()),
+        Some(Host::Ipv4(ip)) => Ok(ip.to_string()),
+        Some(Host::Ipv6(ip)) => Ok(ip.to_string()),
+        None => Err(NetError::InvalidUrl(format!("缺少主机名: {}", url))),
+    }
+}
+
+fn is_secure(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+
+async fn connect_tcp(url: &Url, proxy: &ProxyOptions) -> Result<TcpStream, NetError> {
+    if let Some(tcp) = proxy::connect(url, proxy).await.map_err(NetError::Proxy)? {
+        return Ok(tcp);
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| NetError::InvalidUrl(format!("缺少主机名: {}", url)))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| NetError::InvalidUrl(format!("缺少端口: {}", url)))?;
+
+    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+        .await
+        .map_err(NetError::Connect)?;
+    tcp.set_nodelay(true).ok();
+    Ok(tcp)
+}
+
+/// 按地址的协议建立 TCP 连接，https/wss 时完成 TLS 握手
+pub async fn connect(url: &Url, tls: &TlsOptions, proxy: &ProxyOptions) -> Result<NetStream, NetError> {
+    connect_inspect(url, tls, proxy).await.map(|(stream, _)| stream)
+}
+
+/// 与 [`connect`] 相同，同时返回服务器出示的证书指纹
+pub async fn connect_inspect(
+    url: &Url,
+    tls: &TlsOptions,
+    proxy: &ProxyOptions,
+) -> Result<(NetStream, Option<CertificateInfo>), NetError> {
+    let tcp = connect_tcp(url, proxy).await?;
+
+    if !is_secure(url) {
+        return Ok((MaybeTlsStream::Plain(tcp), None));
+    }
+
+    let (stream, certificate) = tls::connect(&tls_domain(url)?, tcp, tls)
+        .await
+        .map_err(NetError::Tls)?;
+    Ok((MaybeTlsStream::NativeTls(stream), certificate))
+}
+
+/// 获取服务器证书的指纹（不校验证书），用于在握手失败时提示用户固定证书
+pub async fn probe_certificate(url: &Url, proxy: &ProxyOptions) -> Option<CertificateInfo> {
+    if !is_secure(url) {
+        return None;
+    }
+    let tcp = connect_tcp(url, proxy).await.ok()?;
+    tls::probe_certificate(&tls_domain(url).ok()?, tcp).await
+}
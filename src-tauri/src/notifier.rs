@@ -0,0 +1,255 @@
+use crate::app_rules::ClickAction;
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+/// 同时等待点击的通知数量上限。紧急通知不会自动消失，每个等待都会一直占用一个线程
+const MAX_CLICK_WAITERS: usize = 8;
+
+/// 正在等待点击的通知数量
+static CLICK_WAITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// 下载的通知大图保留的时间，过期的文件在下次下载时清理
+const IMAGE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 一条要显示的系统通知
+pub struct Notice {
+    pub title: String,
+    pub body: String,
+    pub icon: Option<String>,
+    pub sound: Option<String>,
+    /// 紧急通知，不会自动消失
+    pub critical: bool,
+    /// 点击后的行为和对应的消息
+    pub click: ClickAction,
+    pub click_url: Option<url::Url>,
+    /// extras 中的大图，下载到本地后显示在通知中
+    pub image: Option<url::Url>,
+    pub profile_id: String,
+    pub message_id: u64,
+}
+
+/// 点击通知后要求主窗口定位到的消息
+#[derive(Clone, Serialize, Debug)]
+struct FocusMessageEvent {
+    profile_id: String,
+    id: u64,
+}
+
+/// 占用一个点击等待名额，释放时归还
+struct ClickWaiter;
+
+impl ClickWaiter {
+    fn acquire() -> Option<Self> {
+        CLICK_WAITERS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                (count < MAX_CLICK_WAITERS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| ClickWaiter)
+    }
+}
+
+impl Drop for ClickWaiter {
+    fn drop(&mut self) {
+        CLICK_WAITERS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 显示系统通知，并在后台等待用户点击。
+///
+/// 通知插件在桌面端不提供点击回调，这里直接使用 notify-rust，设置与插件保持一致。
+/// 有大图时先下载到本地，下载失败则只显示文字
+pub fn show(app: &tauri::AppHandle, notice: Notice) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let image_path = match &notice.image {
+            Some(url) => download_image(&app, &notice, url).await,
+            None => None,
+        };
+        display(app, notice, image_path);
+    });
+}
+
+fn display(app: tauri::AppHandle, notice: Notice, image_path: Option<String>) {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(&notice.title).body(&notice.body);
+    match &notice.icon {
+        Some(icon) => notification.icon(icon),
+        None => notification.auto_icon(),
+    };
+    if let Some(path) = &image_path {
+        notification.image_path(path);
+    }
+    if let Some(sound) = &notice.sound {
+        notification.sound_name(sound);
+    }
+    if notice.critical {
+        // macOS 不支持设置紧急级别和显示时长
+        #[cfg(not(target_os = "macos"))]
+        notification
+            .urgency(notify_rust::Urgency::Critical)
+            .timeout(notify_rust::Timeout::Never);
+    }
+    // XDG 只有声明了 default 动作，点击通知本身才会回调
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.action("default", "查看");
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(exe) = tauri::utils::platform::current_exe() {
+            let dir = exe.parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+            // 只有安装后的程序才设置 AppUserModelID，开发时使用默认值
+            if !(dir.ends_with("target\\debug") || dir.ends_with("target\\release")) {
+                notification.app_id(&app.config().identifier);
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = notify_rust::set_application(if tauri::is_dev() {
+            "com.apple.Terminal"
+        } else {
+            &app.config().identifier
+        });
+    }
+
+    // 等待点击会阻塞到通知关闭为止，超过上限时只显示通知，不再响应点击
+    let waiter = ClickWaiter::acquire();
+    tauri::async_runtime::spawn_blocking(move || match notification.show() {
+        Ok(handle) => {
+            println!("✅ 系统通知已成功发送");
+            let Some(_waiter) = waiter else {
+                println!("⚠️ 等待点击的通知过多，这条通知不响应点击");
+                return;
+            };
+            // Windows 的 wait_for_action 把点击通知本身当作关闭，需要看完整的响应
+            #[cfg(target_os = "windows")]
+            let _ = handle.wait_for_response(|response: &notify_rust::NotificationResponse| {
+                if matches!(
+                    response,
+                    notify_rust::NotificationResponse::Default | notify_rust::NotificationResponse::Action(_)
+                ) {
+                    on_click(&app, notice);
+                }
+            });
+            #[cfg(not(target_os = "windows"))]
+            handle.wait_for_action(|action| {
+                if action != "__closed" {
+                    on_click(&app, notice);
+                }
+            });
+        }
+        Err(e) => eprintln!("❌ 发送通知失败: {:?}", e),
+    });
+}
+
+fn on_click(app: &tauri::AppHandle, notice: Notice) {
+    println!("🖱️ 点击了消息 {} 的通知", notice.message_id);
+
+    // 点击即视为已读
+    if let Some(store) = &app.state::<crate::AppState>().store {
+        if store
+            .mark_read(&notice.profile_id, &[notice.message_id], true)
+            .unwrap_or(0)
+            > 0
+        {
+            crate::publish_unread(app);
+        }
+    }
+
+    // click_url 只会是 http/https 地址，与 opener 插件默认允许的范围一致
+    if notice.click == ClickAction::OpenUrl {
+        if let Some(url) = &notice.click_url {
+            use tauri_plugin_opener::OpenerExt;
+
+            match app.opener().open_url(url.as_str(), None::<&str>) {
+                Ok(()) => return,
+                Err(e) => eprintln!("❌ 打开链接失败，改为定位到消息: {}", e),
+            }
+        }
+    }
+
+    focus_message(app, notice.profile_id, notice.message_id);
+}
+
+/// 把主窗口切换到前台，并要求它定位到指定的消息
+pub fn focus_message(app: &tauri::AppHandle, profile_id: String, id: u64) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    app.emit("focus-message", FocusMessageEvent { profile_id, id }).ok();
+}
+
+fn image_dir() -> String {
+    format!("{}/notification_images", crate::get_config_dir())
+}
+
+/// 用该服务器的代理设置下载大图，保存到 `notification_images/{profile_id}-{id}.{ext}`，失败时返回 None
+async fn download_image(app: &tauri::AppHandle, notice: &Notice, url: &url::Url) -> Option<String> {
+    let client = match crate::rest_client(&app.state::<crate::AppState>(), &notice.profile_id) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("⚠️ 无法下载通知大图: {}", e);
+            return None;
+        }
+    };
+    let (content_type, bytes) = match client.fetch_image(url).await {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("⚠️ 下载通知大图失败 ({}): {}", url, e);
+            return None;
+        }
+    };
+
+    // 扩展名按 Content-Type 决定，地址中的扩展名不一定可靠
+    let subtype = content_type
+        .trim_start_matches("image/")
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let extension = match subtype {
+        "jpeg" | "jpg" => "jpg",
+        "gif" => "gif",
+        "webp" => "webp",
+        "bmp" => "bmp",
+        _ => "png",
+    };
+    let dir = image_dir();
+    let path = format!("{}/{}-{}.{}", dir, notice.profile_id, notice.message_id, extension);
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        prune_images(&dir).await;
+        tokio::fs::write(&path, &bytes).await
+    };
+    match written.await {
+        Ok(()) => Some(path),
+        Err(e) => {
+            eprintln!("❌ 保存通知大图失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 删除超过 [`IMAGE_MAX_AGE`] 的大图，通知早已消失，不再需要
+async fn prune_images(dir: &str) {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let expired = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > IMAGE_MAX_AGE);
+        if expired {
+            let _ = tokio::fs::remove_file(entry.path()).await;
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 收到消息后如何提醒
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotifyBehavior {
+    /// 只保存到历史，不弹出系统通知
+    pub silent: bool,
+    /// 提示音名称（`default` 为系统默认提示音），为空时不播放声音
+    pub sound: Option<String>,
+    /// 以紧急级别显示，通知不会自动消失
+    pub critical: bool,
+    /// 把主窗口切换到前台并定位到该消息
+    pub bring_to_front: bool,
+}
+
+/// 一段优先级范围对应的提醒方式
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PriorityRule {
+    pub min_priority: u32,
+    /// 为空表示不设上限
+    #[serde(default)]
+    pub max_priority: Option<u32>,
+    #[serde(default)]
+    pub behavior: NotifyBehavior,
+}
+
+impl PriorityRule {
+    fn contains(&self, priority: u32) -> bool {
+        priority >= self.min_priority && self.max_priority.map_or(true, |max| priority <= max)
+    }
+
+    fn describe(&self) -> String {
+        match self.max_priority {
+            Some(max) => format!("{}-{}", self.min_priority, max),
+            None => format!("{}+", self.min_priority),
+        }
+    }
+}
+
+/// 优先级到提醒方式的映射，对所有服务器生效。不在任何范围内的消息只显示通知，不播放声音
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct PriorityMap {
+    pub rules: Vec<PriorityRule>,
+}
+
+impl Default for PriorityMap {
+    /// 与之前的固定逻辑一致：优先级 5 及以上播放默认提示音
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                PriorityRule {
+                    min_priority: 0,
+                    max_priority: Some(4),
+                    behavior: NotifyBehavior::default(),
+                },
+                PriorityRule {
+                    min_priority: 5,
+                    max_priority: None,
+                    behavior: NotifyBehavior {
+                        sound: Some("default".to_string()),
+                        ..NotifyBehavior::default()
+                    },
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PriorityError {
+    /// 范围的下限大于上限
+    InvalidRange(String),
+    /// 两个范围有重叠
+    Overlap(String, String),
+}
+
+impl fmt::Display for PriorityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriorityError::InvalidRange(range) => write!(f, "优先级范围 {} 无效：下限大于上限", range),
+            PriorityError::Overlap(a, b) => write!(f, "优先级范围 {} 与 {} 重叠", a, b),
+        }
+    }
+}
+
+impl std::error::Error for PriorityError {}
+
+impl PriorityMap {
+    /// 查找优先级对应的提醒方式
+    pub fn behavior(&self, priority: u32) -> NotifyBehavior {
+        self.rules
+            .iter()
+            .find(|rule| rule.contains(priority))
+            .map(|rule| rule.behavior.clone())
+            .unwrap_or_default()
+    }
+
+    /// 检查范围是否有效且互不重叠，并把空的提示音名称规范为不播放声音
+    pub fn validate(mut self) -> Result<Self, PriorityError> {
+        for rule in &mut self.rules {
+            if rule.max_priority.is_some_and(|max| max < rule.min_priority) {
+                return Err(PriorityError::InvalidRange(rule.describe()));
+            }
+            rule.behavior.sound = rule
+                .behavior
+                .sound
+                .take()
+                .map(|sound| sound.trim().to_string())
+                .filter(|sound| !sound.is_empty());
+        }
+
+        self.rules.sort_by_key(|rule| rule.min_priority);
+        for pair in self.rules.windows(2) {
+            if pair[1].min_priority <= pair[0].max_priority.unwrap_or(u32::MAX) {
+                return Err(PriorityError::Overlap(pair[0].describe(), pair[1].describe()));
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(min_priority: u32, max_priority: Option<u32>, sound: Option<&str>) -> PriorityRule {
+        PriorityRule {
+            min_priority,
+            max_priority,
+            behavior: NotifyBehavior {
+                sound: sound.map(str::to_string),
+                ..NotifyBehavior::default()
+            },
+        }
+    }
+
+    fn validate(rules: Vec<PriorityRule>) -> Result<PriorityMap, PriorityError> {
+        PriorityMap { rules }.validate()
+    }
+
+    #[test]
+    fn sorts_rules_and_normalizes_sound() {
+        let map = validate(vec![
+            rule(8, None, Some(" alarm ")),
+            rule(0, Some(3), Some("  ")),
+            rule(4, Some(7), None),
+        ])
+        .unwrap();
+
+        let ranges: Vec<String> = map.rules.iter().map(PriorityRule::describe).collect();
+        assert_eq!(ranges, ["0-3", "4-7", "8+"]);
+        assert_eq!(map.rules[0].behavior.sound, None);
+        assert_eq!(map.rules[2].behavior.sound.as_deref(), Some("alarm"));
+        assert_eq!(map.behavior(10).sound.as_deref(), Some("alarm"));
+    }
+
+    #[test]
+    fn leaves_gaps_without_sound() {
+        let map = validate(vec![rule(0, Some(2), None), rule(6, None, Some("default"))]).unwrap();
+        assert_eq!(map.behavior(4), NotifyBehavior::default());
+        assert_eq!(map.behavior(6).sound.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn rejects_inverted_range() {
+        let error = validate(vec![rule(5, Some(3), None)]).unwrap_err();
+        assert!(matches!(&error, PriorityError::InvalidRange(range) if range == "5-3"));
+        assert_eq!(error.to_string(), "优先级范围 5-3 无效：下限大于上限");
+    }
+
+    #[test]
+    fn rejects_overlapping_ranges() {
+        let error = validate(vec![rule(5, None, None), rule(0, Some(5), None)]).unwrap_err();
+        assert!(matches!(&error, PriorityError::Overlap(a, b) if a == "0-5" && b == "5+"));
+
+        // 不设上限的范围覆盖后面的所有优先级
+        let error = validate(vec![rule(0, None, None), rule(9, Some(10), None)]).unwrap_err();
+        assert!(matches!(&error, PriorityError::Overlap(a, b) if a == "0+" && b == "9-10"));
+    }
+
+    #[test]
+    fn default_map_is_valid() {
+        let map = PriorityMap::default().validate().unwrap();
+        assert_eq!(map.behavior(4).sound, None);
+        assert_eq!(map.behavior(5).sound.as_deref(), Some("default"));
+    }
+}
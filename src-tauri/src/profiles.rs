@@ -0,0 +1,288 @@
+use crate::app_rules::AppRule;
+use crate::filters::FilterRule;
+use crate::priority::PriorityMap;
+use crate::proxy::ProxyOptions;
+use crate::retention::RetentionPolicy;
+use crate::server_url::ServerUrl;
+use crate::tls::TlsOptions;
+use crate::ConnectionConfig;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// 从旧版单服务器配置迁移而来的配置使用的 id
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+/// 新建配置时依次选用的颜色
+const PROFILE_COLORS: [&str; 6] = ["#3b82f6", "#10b981", "#f59e0b", "#ef4444", "#8b5cf6", "#ec4899"];
+
+/// 一个 Gotify 服务器的配置
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ServerProfile {
+    pub id: String,
+    pub name: String,
+    /// 界面中区分服务器的颜色，形如 `#3b82f6`
+    #[serde(default = "default_color")]
+    pub color: String,
+    /// 关闭后不再自动连接该服务器
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub connection: ConnectionConfig,
+}
+
+fn default_color() -> String {
+    PROFILE_COLORS[0].to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 编辑服务器配置时提交的字段，未提供的字段保持不变
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub enabled: Option<bool>,
+    pub server_url: Option<String>,
+    pub client_token: Option<String>,
+    pub tls: Option<TlsOptions>,
+    pub proxy: Option<ProxyOptions>,
+}
+
+impl ProfileUpdate {
+    /// 应用到配置上，`server_url` 需要调用方事先校验并规范化。
+    ///
+    /// 返回服务器地址或 Token 是否发生了变化：变化后原来的同步进度和消息 id 都不再适用
+    pub fn apply(self, profile: &mut ServerProfile) -> bool {
+        let before = server_identity(&profile.connection);
+        if let Some(name) = self.name {
+            profile.name = name;
+        }
+        if let Some(color) = self.color {
+            profile.color = color;
+        }
+        if let Some(enabled) = self.enabled {
+            profile.enabled = enabled;
+        }
+        if let Some(server_url) = self.server_url {
+            profile.connection.server_url = server_url;
+        }
+        if let Some(client_token) = self.client_token {
+            profile.connection.client_token = client_token;
+        }
+        if let Some(tls) = self.tls {
+            profile.connection.tls = tls;
+        }
+        if let Some(proxy) = self.proxy {
+            profile.connection.proxy = proxy;
+        }
+        server_identity(&profile.connection) != before
+    }
+}
+
+/// 规范化后的服务器地址和 Token。旧版配置中的地址可能没有规范化，无法解析时按原样比较
+fn server_identity(connection: &ConnectionConfig) -> (String, String) {
+    let server_url = ServerUrl::parse(&connection.server_url)
+        .map(|url| url.normalized())
+        .unwrap_or_else(|_| connection.server_url.clone());
+    (server_url, connection.client_token.clone())
+}
+
+/// 保存在 `.gotify_config.json` 中的全部服务器配置，按界面显示顺序排列
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct AppConfig {
+    pub profiles: Vec<ServerProfile>,
+    /// 本地消息历史的保留策略，对所有服务器生效
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// 针对单个应用的通知设置
+    #[serde(default)]
+    pub app_rules: Vec<AppRule>,
+    /// 按优先级决定提醒方式
+    #[serde(default)]
+    pub priorities: PriorityMap,
+    /// 按顺序执行的过滤规则
+    #[serde(default)]
+    pub filters: Vec<FilterRule>,
+}
+
+impl AppConfig {
+    pub fn profile(&self, id: &str) -> Option<&ServerProfile> {
+        self.profiles.iter().find(|p| p.id == id)
+    }
+
+    pub fn profile_mut(&mut self, id: &str) -> Option<&mut ServerProfile> {
+        self.profiles.iter_mut().find(|p| p.id == id)
+    }
+
+    /// 按 id 查找配置，没有指定 id 时返回第一个
+    pub fn resolve(&self, id: Option<&str>) -> Option<&ServerProfile> {
+        match id {
+            Some(id) => self.profile(id),
+            None => self.profiles.first(),
+        }
+    }
+
+    pub fn resolve_mut(&mut self, id: Option<&str>) -> Option<&mut ServerProfile> {
+        match id {
+            Some(id) => self.profile_mut(id),
+            None => self.profiles.first_mut(),
+        }
+    }
+
+    /// 生成一个未被使用的配置 id
+    pub fn new_profile_id(&self) -> String {
+        let mut rng = rand::thread_rng();
+        loop {
+            let id = format!("{:08x}", rng.gen::<u32>());
+            if self.profile(&id).is_none() {
+                return id;
+            }
+        }
+    }
+
+    /// 为新配置挑选颜色，尽量不与已有配置重复
+    pub fn next_color(&self) -> String {
+        PROFILE_COLORS
+            .iter()
+            .find(|color| !self.profiles.iter().any(|p| p.color == **color))
+            .unwrap_or(&PROFILE_COLORS[self.profiles.len() % PROFILE_COLORS.len()])
+            .to_string()
+    }
+}
+
+/// 配置文件的两种格式：当前的多服务器格式和旧版的单服务器格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredConfig {
+    Profiles(AppConfig),
+    Legacy(Box<ConnectionConfig>),
+}
+
+/// 把旧版的单服务器配置转换为默认配置
+fn migrate(connection: ConnectionConfig) -> AppConfig {
+    AppConfig {
+        profiles: vec![ServerProfile {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "默认服务器".to_string(),
+            color: default_color(),
+            enabled: true,
+            connection,
+        }],
+        retention: RetentionPolicy::default(),
+        app_rules: Vec::new(),
+        priorities: PriorityMap::default(),
+        filters: Vec::new(),
+    }
+}
+
+fn config_path() -> String {
+    format!("{}/.gotify_config.json", crate::get_config_dir())
+}
+
+pub fn load_config() -> AppConfig {
+    let config_path = config_path();
+
+    match std::fs::read_to_string(&config_path) {
+        Ok(content) => match serde_json::from_str::<StoredConfig>(&content) {
+            Ok(StoredConfig::Profiles(config)) => {
+                println!("✅ 从文件加载配置成功: {} ({} 个服务器)", config_path, config.profiles.len());
+                config
+            }
+            Ok(StoredConfig::Legacy(connection)) => {
+                println!("✅ 从文件加载旧版配置，迁移为默认服务器: {}", config_path);
+                migrate(*connection)
+            }
+            Err(e) => {
+                eprintln!("❌ 解析配置文件失败: {}", e);
+                // 如果配置文件解析失败，尝试从环境变量加载
+                load_config_from_env()
+            }
+        },
+        Err(_) => {
+            println!("ℹ️ 未找到配置文件，尝试从环境变量加载");
+            load_config_from_env()
+        }
+    }
+}
+
+fn load_config_from_env() -> AppConfig {
+    let server_url = std::env::var("GOTIFY_SERVER_URL")
+        .or_else(|_| std::env::var("GOTIFY_URL"))
+        .unwrap_or_else(|_| "http://localhost:8080".to_string());
+
+    let client_token = std::env::var("GOTIFY_CLIENT_TOKEN")
+        .or_else(|_| std::env::var("GOTIFY_TOKEN"))
+        .unwrap_or_else(|_| "".to_string());
+
+    // 只有当Token不为空时才返回配置
+    if !client_token.is_empty() {
+        println!("✅ 从环境变量加载配置成功");
+        migrate(ConnectionConfig::new(server_url, client_token))
+    } else {
+        println!("ℹ️ 未找到有效的配置，请通过设置界面配置");
+        AppConfig::default()
+    }
+}
+
+pub fn save_config(config: &AppConfig) -> std::io::Result<()> {
+    let config_path = config_path();
+    std::fs::write(&config_path, serde_json::to_string_pretty(config).unwrap())?;
+    println!("✅ 配置已保存到文件: {}", config_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(server_url: &str, client_token: &str) -> ServerProfile {
+        ServerProfile {
+            id: "home".to_string(),
+            name: "家里".to_string(),
+            color: default_color(),
+            enabled: true,
+            connection: ConnectionConfig::new(server_url.to_string(), client_token.to_string()),
+        }
+    }
+
+    #[test]
+    fn detects_server_change() {
+        let mut home = profile("https://gotify.example.com", "token");
+
+        let unchanged = ProfileUpdate {
+            name: Some("新名称".to_string()),
+            server_url: Some("https://gotify.example.com".to_string()),
+            client_token: Some("token".to_string()),
+            proxy: Some(ProxyOptions::default()),
+            ..ProfileUpdate::default()
+        };
+        assert!(!unchanged.apply(&mut home));
+        assert_eq!(home.name, "新名称");
+
+        let new_url = ProfileUpdate {
+            server_url: Some("https://other.example.com".to_string()),
+            ..ProfileUpdate::default()
+        };
+        assert!(new_url.apply(&mut home));
+
+        let new_token = ProfileUpdate {
+            client_token: Some("another".to_string()),
+            ..ProfileUpdate::default()
+        };
+        assert!(new_token.apply(&mut home));
+    }
+
+    #[test]
+    fn compares_legacy_urls_after_normalizing() {
+        let mut legacy = profile("gotify.example.com/", "token");
+        let update = ProfileUpdate {
+            server_url: Some(ServerUrl::parse("gotify.example.com/").unwrap().normalized()),
+            ..ProfileUpdate::default()
+        };
+        assert!(!update.apply(&mut legacy));
+        assert_eq!(legacy.connection.server_url, "https://gotify.example.com");
+    }
+}
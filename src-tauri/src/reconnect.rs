@@ -0,0 +1,124 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 断线重连策略：指数退避 + 随机抖动
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// 是否启用自动重连
+    pub enabled: bool,
+    /// 第一次重连前的等待时间（毫秒）
+    pub initial_delay_ms: u64,
+    /// 退避等待时间上限（毫秒）
+    pub max_delay_ms: u64,
+    /// 每次失败后的等待时间倍数
+    pub multiplier: f64,
+    /// 抖动比例（0.0 ~ 1.0），避免多个客户端同时重连
+    pub jitter: f64,
+    /// 连续失败的最大重试次数，None 表示无限重试
+    pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_delay_ms: 1_000,
+            max_delay_ms: 60_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 计算第 `attempt` 次重连（从 1 开始）前的等待时间
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = self.initial_delay_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        let capped = base.min(self.max_delay_ms as f64);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+
+        Duration::from_millis((capped * factor).min(self.max_delay_ms as f64) as u64)
+    }
+
+    /// 是否已超过最大重试次数
+    pub fn exhausted(&self, attempt: u32) -> bool {
+        !self.enabled || self.max_retries.is_some_and(|max| attempt > max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> ReconnectPolicy {
+        ReconnectPolicy {
+            jitter,
+            ..ReconnectPolicy::default()
+        }
+    }
+
+    fn millis(policy: &ReconnectPolicy, attempt: u32) -> u64 {
+        policy.delay_for(attempt).as_millis() as u64
+    }
+
+    #[test]
+    fn grows_exponentially_up_to_cap() {
+        let policy = policy(0.0);
+        let delays: Vec<u64> = (1..=8).map(|attempt| millis(&policy, attempt)).collect();
+        assert_eq!(delays, [1_000, 2_000, 4_000, 8_000, 16_000, 32_000, 60_000, 60_000]);
+
+        // 第 0 次按第 1 次计算，次数很大时不会溢出
+        assert_eq!(millis(&policy, 0), 1_000);
+        assert_eq!(millis(&policy, u32::MAX), 60_000);
+    }
+
+    #[test]
+    fn multiplier_below_one_keeps_initial_delay() {
+        let policy = ReconnectPolicy {
+            multiplier: 0.5,
+            ..policy(0.0)
+        };
+        assert_eq!(millis(&policy, 5), 1_000);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let jittered = policy(0.2);
+        for _ in 0..200 {
+            let delay = millis(&jittered, 3);
+            assert!((3_199..=4_800).contains(&delay), "{}", delay);
+            // 达到上限后抖动也不会超过上限
+            let capped = millis(&jittered, 10);
+            assert!((47_999..=60_000).contains(&capped), "{}", capped);
+        }
+
+        // 抖动比例超出范围时按 1.0 处理
+        let wide = policy(3.0);
+        for _ in 0..200 {
+            assert!(millis(&wide, 2) <= 4_000);
+        }
+    }
+
+    #[test]
+    fn stops_after_max_retries() {
+        let mut policy = ReconnectPolicy::default();
+        assert!(!policy.exhausted(u32::MAX));
+
+        policy.max_retries = Some(3);
+        assert!(!policy.exhausted(3));
+        assert!(policy.exhausted(4));
+
+        policy.enabled = false;
+        assert!(policy.exhausted(0));
+    }
+}
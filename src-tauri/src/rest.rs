@@ -0,0 +1,301 @@
+use crate::net::{self, NetError};
+use crate::proxy::ProxyOptions;
+use crate::server_url::{ServerUrl, UrlError};
+use crate::tls::TlsOptions;
+use crate::{ConnectionConfig, GotifyMessage, TokenTransport};
+use http_body_util::{BodyExt, Empty, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+use url::Url;
+
+/// 单次 REST 请求的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 每页拉取的消息数量（Gotify 上限为 200）
+pub const PAGE_LIMIT: usize = 100;
+
+/// 消息 extras 中图片的大小上限，地址由发送方指定，不能无限制地读入内存
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum RestError {
+    Net(NetError),
+    Http(String),
+    Status(StatusCode),
+    Decode(String),
+    Timeout,
+    InvalidUrl(UrlError),
+    /// 响应超过大小上限（字节）
+    TooLarge(usize),
+    /// 要求图片时返回了其他类型，附带 Content-Type
+    NotImage(String),
+}
+
+impl fmt::Display for RestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestError::Net(e) => write!(f, "{}", e),
+            RestError::Http(e) => write!(f, "HTTP 请求失败: {}", e),
+            RestError::Status(status) => write!(f, "服务器返回 {}", status),
+            RestError::Decode(e) => write!(f, "解析响应失败: {}", e),
+            RestError::Timeout => write!(f, "请求超时"),
+            RestError::InvalidUrl(e) => write!(f, "{}", e),
+            RestError::TooLarge(limit) => write!(f, "响应超过 {} MB 上限", limit / 1024 / 1024),
+            RestError::NotImage(content_type) => write!(f, "响应不是图片 (Content-Type: {})", content_type),
+        }
+    }
+}
+
+impl std::error::Error for RestError {}
+
+impl From<NetError> for RestError {
+    fn from(e: NetError) -> Self {
+        RestError::Net(e)
+    }
+}
+
+impl From<hyper::Error> for RestError {
+    fn from(e: hyper::Error) -> Self {
+        RestError::Http(e.to_string())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Paging {
+    pub since: u64,
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PagedMessages {
+    pub messages: Vec<GotifyMessage>,
+    pub paging: Paging,
+}
+
+/// 服务器上的一个应用
+#[derive(Deserialize, Debug, Clone)]
+pub struct Application {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// 图标的相对路径，例如 `image/xxx.png`
+    #[serde(default)]
+    pub image: String,
+}
+
+/// Gotify REST API 客户端
+pub struct GotifyClient {
+    server: ServerUrl,
+    token: String,
+    token_transport: TokenTransport,
+    tls: TlsOptions,
+    proxy: ProxyOptions,
+}
+
+impl GotifyClient {
+    pub fn new(config: &ConnectionConfig) -> Result<Self, RestError> {
+        let server = ServerUrl::parse(&config.server_url).map_err(RestError::InvalidUrl)?;
+
+        Ok(Self {
+            server,
+            token: config.client_token.clone(),
+            token_transport: config.token_transport,
+            tls: config.tls.clone(),
+            proxy: config.proxy.clone(),
+        })
+    }
+
+    async fn request<T: DeserializeOwned>(&self, method: Method, path: &str) -> Result<T, RestError> {
+        let body = self.request_bytes(method, path).await?;
+        serde_json::from_slice(&body).map_err(|e| RestError::Decode(e.to_string()))
+    }
+
+    async fn request_bytes(&self, method: Method, path: &str) -> Result<Bytes, RestError> {
+        let url = self.server.endpoint(path).map_err(RestError::InvalidUrl)?;
+        let request = async {
+            let response = self.send(method, url).await?;
+            Ok(response.into_body().collect().await?.to_bytes())
+        };
+        tokio::time::timeout(REQUEST_TIMEOUT, request)
+            .await
+            .map_err(|_| RestError::Timeout)?
+    }
+
+    /// 发送请求，状态码不是 2xx 时返回错误。
+    ///
+    /// 只有与服务器同源时才带上 Token 并使用服务器的 TLS 设置，
+    /// 避免把 Token 发给第三方，也避免用服务器的证书指纹去校验其他主机
+    async fn send(&self, method: Method, mut url: Url) -> Result<Response<Incoming>, RestError> {
+        let same_origin = self.server.same_origin(&url);
+        if same_origin && self.token_transport == TokenTransport::Query {
+            url.query_pairs_mut().append_pair("token", &self.token);
+        }
+
+        let default_tls = TlsOptions::default();
+        let tls = if same_origin { &self.tls } else { &default_tls };
+        let stream = net::connect(&url, tls, &self.proxy).await?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("❌ REST 连接错误: {}", e);
+            }
+        });
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let path_and_query = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path_and_query)
+            .header(hyper::header::HOST, host)
+            .header(hyper::header::ACCEPT, "application/json, image/*");
+        if same_origin && self.token_transport == TokenTransport::Header {
+            request = request.header("X-Gotify-Key", &self.token);
+        }
+        let request = request
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| RestError::Http(e.to_string()))?;
+
+        let response = sender.send_request(request).await?;
+        if !response.status().is_success() {
+            return Err(RestError::Status(response.status()));
+        }
+        Ok(response)
+    }
+
+    /// 获取一页消息：按 id 从新到旧，`since` 为 0 时从最新一条开始
+    pub async fn messages(&self, limit: usize, since: u64) -> Result<PagedMessages, RestError> {
+        let path = if since > 0 {
+            format!("message?limit={}&since={}", limit, since)
+        } else {
+            format!("message?limit={}", limit)
+        };
+        self.request(Method::GET, &path).await
+    }
+
+    /// 当前用户可见的所有应用
+    pub async fn applications(&self) -> Result<Vec<Application>, RestError> {
+        self.request(Method::GET, "application").await
+    }
+
+    /// 下载应用图标，`path` 为 [`Application::image`]
+    pub async fn application_image(&self, path: &str) -> Result<Bytes, RestError> {
+        self.request_bytes(Method::GET, path).await
+    }
+
+    /// 下载消息 extras 中的图片，经过与该服务器相同的代理，返回 Content-Type 和图片数据。
+    ///
+    /// 只接受 `image/*` 类型且不超过 [`MAX_IMAGE_BYTES`] 的响应
+    pub async fn fetch_image(&self, url: &Url) -> Result<(String, Bytes), RestError> {
+        let request = async {
+            let response = self.send(Method::GET, url.clone()).await?;
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_ascii_lowercase)
+            };
+
+            let content_type = header(hyper::header::CONTENT_TYPE).unwrap_or_default();
+            if !content_type.starts_with("image/") {
+                return Err(RestError::NotImage(content_type));
+            }
+            // 有 Content-Length 时不必读取就能拒绝，没有时边读边检查
+            if header(hyper::header::CONTENT_LENGTH)
+                .and_then(|length| length.parse::<u64>().ok())
+                .is_some_and(|length| length > MAX_IMAGE_BYTES as u64)
+            {
+                return Err(RestError::TooLarge(MAX_IMAGE_BYTES));
+            }
+
+            let body = Limited::new(response.into_body(), MAX_IMAGE_BYTES)
+                .collect()
+                .await
+                .map_err(|e| {
+                    if e.is::<LengthLimitError>() {
+                        RestError::TooLarge(MAX_IMAGE_BYTES)
+                    } else {
+                        RestError::Http(e.to_string())
+                    }
+                })?;
+            Ok((content_type, body.to_bytes()))
+        };
+        tokio::time::timeout(REQUEST_TIMEOUT, request)
+            .await
+            .map_err(|_| RestError::Timeout)?
+    }
+
+    /// 删除一条消息
+    pub async fn delete_message(&self, id: u64) -> Result<(), RestError> {
+        self.request_bytes(Method::DELETE, &format!("message/{}", id)).await?;
+        Ok(())
+    }
+
+    /// 删除某个应用的所有消息
+    pub async fn delete_application_messages(&self, appid: u64) -> Result<(), RestError> {
+        self.request_bytes(Method::DELETE, &format!("application/{}/message", appid)).await?;
+        Ok(())
+    }
+
+    /// 删除当前用户的所有消息
+    pub async fn delete_all_messages(&self) -> Result<(), RestError> {
+        self.request_bytes(Method::DELETE, "message").await?;
+        Ok(())
+    }
+
+    /// 服务器上最新一条消息的 id
+    pub async fn latest_message_id(&self) -> Result<Option<u64>, RestError> {
+        let page = self.messages(1, 0).await?;
+        Ok(page.messages.first().map(|m| m.id))
+    }
+
+    /// 分页获取 id 大于 `after_id` 的消息，按 id 从旧到新返回。
+    /// 第二个返回值表示是否因超过 `max` 条而被截断
+    pub async fn messages_after(
+        &self,
+        after_id: u64,
+        max: usize,
+    ) -> Result<(Vec<GotifyMessage>, bool), RestError> {
+        let mut collected = Vec::new();
+        let mut since = 0;
+
+        'pages: loop {
+            let page = self.messages(PAGE_LIMIT, since).await?;
+            let exhausted = page.paging.next.is_none() || page.messages.is_empty();
+
+            for message in page.messages {
+                if message.id <= after_id {
+                    break 'pages;
+                }
+                if collected.len() >= max {
+                    collected.reverse();
+                    return Ok((collected, true));
+                }
+                collected.push(message);
+            }
+
+            if exhausted {
+                break;
+            }
+            since = page.paging.since;
+        }
+
+        collected.reverse();
+        Ok((collected, false))
+    }
+}
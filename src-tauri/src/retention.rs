@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Manager;
+
+/// 本地消息历史的保留策略。标记为保留（keep）的消息不受任何规则影响
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// 最多保存的消息总数，超出时删除最旧的消息；默认不限制，由用户在设置中开启
+    pub max_messages: Option<u64>,
+    /// 消息最多保存的天数；设置了单独保存天数的应用不受此限制
+    pub max_age_days: Option<u32>,
+    /// 针对单个应用的规则
+    pub apps: Vec<AppRetention>,
+    /// 后台清理的间隔（分钟）
+    pub prune_interval_minutes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_messages: None,
+            max_age_days: None,
+            apps: Vec::new(),
+            prune_interval_minutes: 60,
+        }
+    }
+}
+
+/// 单个应用的保留规则
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AppRetention {
+    /// 只对该服务器生效，为空时对所有服务器上 id 相同的应用生效
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    pub appid: u64,
+    /// 该应用的消息最多保存的天数，代替全局的天数限制
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// 该应用最多保存的消息数
+    #[serde(default)]
+    pub max_count: Option<u64>,
+}
+
+/// 一次清理删除的消息数
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct PruneReport {
+    /// 清理完成的时间（Unix 毫秒时间戳）
+    pub finished_at: u64,
+    /// 按应用规则删除的消息数
+    pub by_app: u64,
+    /// 按全局天数删除的消息数
+    pub by_age: u64,
+    /// 按全局总数删除的消息数
+    pub by_count: u64,
+}
+
+impl PruneReport {
+    pub fn total(&self) -> u64 {
+        self.by_app + self.by_age + self.by_count
+    }
+}
+
+/// 按当前配置清理一次消息历史
+pub fn prune_now(app: &tauri::AppHandle) -> Option<PruneReport> {
+    let state = app.state::<crate::AppState>();
+    let store = state.store.as_ref()?;
+    let policy = state.config.lock().unwrap().retention.clone();
+
+    match store.prune(&policy) {
+        Ok(report) => {
+            if report.total() > 0 {
+                println!(
+                    "🧹 已清理 {} 条历史消息（应用规则 {}，过期 {}，超出总数 {}）",
+                    report.total(),
+                    report.by_app,
+                    report.by_age,
+                    report.by_count
+                );
+                // 被清理的消息可能是未读的
+                crate::publish_unread(app);
+            }
+            Some(report)
+        }
+        Err(e) => {
+            eprintln!("❌ 清理历史消息失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 启动后台清理任务：启动时清理一次，之后按配置的间隔定期清理
+pub fn spawn_pruner(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let handle = app.clone();
+            // SQLite 的删除是阻塞操作，放到阻塞线程池中执行
+            let _ = tokio::task::spawn_blocking(move || prune_now(&handle)).await;
+
+            let interval = {
+                let state = app.state::<crate::AppState>();
+                let minutes = state.config.lock().unwrap().retention.prune_interval_minutes;
+                Duration::from_secs(minutes.max(1) * 60)
+            };
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_mean_unlimited() {
+        let policy: RetentionPolicy = serde_json::from_str(r#"{ "apps": [{ "appid": 3 }] }"#).unwrap();
+        assert_eq!((policy.max_messages, policy.max_age_days), (None, None));
+        assert_eq!(policy.prune_interval_minutes, 60);
+
+        let app = &policy.apps[0];
+        assert_eq!((app.profile_id.as_deref(), app.max_age_days, app.max_count), (None, None, None));
+    }
+
+    #[test]
+    fn report_total_adds_all_reasons() {
+        let report = PruneReport {
+            finished_at: 0,
+            by_app: 1,
+            by_age: 2,
+            by_count: 3,
+        };
+        assert_eq!(report.total(), 6);
+    }
+}
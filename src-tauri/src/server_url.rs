@@ -0,0 +1,256 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use url::{Host, Url};
+
+/// 服务器地址校验失败的原因
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UrlError {
+    /// 地址为空
+    Empty,
+    /// 不是 http/https（或 ws/wss）地址
+    UnsupportedScheme(String),
+    /// 缺少主机名
+    MissingHost,
+    /// 主机名或 IP 地址不合法
+    InvalidHost(String),
+    /// 端口不合法
+    InvalidPort,
+    /// 地址中带有用户名或密码
+    HasCredentials,
+    /// 地址中带有查询参数
+    HasQuery,
+    /// 地址中带有 `#` 片段
+    HasFragment,
+    /// 其他无法解析的情况
+    Invalid(String),
+}
+
+impl UrlError {
+    /// 稳定的错误标识，供前端区分错误类型
+    pub fn kind(&self) -> &'static str {
+        match self {
+            UrlError::Empty => "empty",
+            UrlError::UnsupportedScheme(_) => "unsupported_scheme",
+            UrlError::MissingHost => "missing_host",
+            UrlError::InvalidHost(_) => "invalid_host",
+            UrlError::InvalidPort => "invalid_port",
+            UrlError::HasCredentials => "has_credentials",
+            UrlError::HasQuery => "has_query",
+            UrlError::HasFragment => "has_fragment",
+            UrlError::Invalid(_) => "invalid",
+        }
+    }
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::Empty => write!(f, "服务器地址不能为空"),
+            UrlError::UnsupportedScheme(scheme) => {
+                write!(f, "不支持的协议 \"{}\"，请使用 http:// 或 https://", scheme)
+            }
+            UrlError::MissingHost => write!(f, "服务器地址缺少主机名"),
+            UrlError::InvalidHost(host) => write!(f, "无效的主机名或 IP 地址: {}", host),
+            UrlError::InvalidPort => write!(f, "端口号无效，应为 1-65535"),
+            UrlError::HasCredentials => write!(f, "服务器地址中不能包含用户名或密码"),
+            UrlError::HasQuery => write!(f, "服务器地址中不能包含查询参数（?...）"),
+            UrlError::HasFragment => write!(f, "服务器地址中不能包含 # 片段"),
+            UrlError::Invalid(reason) => write!(f, "无效的服务器地址: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+impl Serialize for UrlError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("UrlError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// 规范化后的 Gotify 服务器地址，负责派生 WebSocket 和 REST 端点
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerUrl {
+    /// http/https 地址，路径总是以 `/` 结尾，方便拼接子路径部署下的端点
+    base: Url,
+}
+
+impl ServerUrl {
+    /// 解析用户输入的服务器地址。
+    ///
+    /// 没有写协议时，localhost 和 IP 地址默认使用 http，其他域名默认使用 https。
+    pub fn parse(input: &str) -> Result<Self, UrlError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(UrlError::Empty);
+        }
+
+        let with_scheme = if input.contains("://") {
+            input.to_string()
+        } else {
+            format!("{}://{}", default_scheme(input), input)
+        };
+
+        let mut url = Url::parse(&with_scheme).map_err(|e| match e {
+            url::ParseError::EmptyHost => UrlError::MissingHost,
+            url::ParseError::InvalidPort => UrlError::InvalidPort,
+            url::ParseError::InvalidIpv4Address
+            | url::ParseError::InvalidIpv6Address
+            | url::ParseError::InvalidDomainCharacter
+            | url::ParseError::IdnaError => UrlError::InvalidHost(input.to_string()),
+            e => UrlError::Invalid(e.to_string()),
+        })?;
+
+        let scheme = match url.scheme() {
+            "http" | "ws" => "http",
+            "https" | "wss" => "https",
+            other => return Err(UrlError::UnsupportedScheme(other.to_string())),
+        };
+        if url.scheme() != scheme {
+            // ws/wss 与 http/https 同为特殊协议，默认端口一致，可以直接替换
+            url.set_scheme(scheme)
+                .map_err(|_| UrlError::UnsupportedScheme(url.scheme().to_string()))?;
+        }
+
+        match url.host() {
+            None => return Err(UrlError::MissingHost),
+            Some(Host::Domain("")) => return Err(UrlError::MissingHost),
+            Some(_) => {}
+        }
+        if url.port() == Some(0) {
+            return Err(UrlError::InvalidPort);
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(UrlError::HasCredentials);
+        }
+        if url.query().is_some_and(|q| !q.is_empty()) {
+            return Err(UrlError::HasQuery);
+        }
+        if url.fragment().is_some_and(|f| !f.is_empty()) {
+            return Err(UrlError::HasFragment);
+        }
+        url.set_query(None);
+        url.set_fragment(None);
+
+        // 合并多余的斜杠，并保证路径以 / 结尾
+        let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+        let path = if segments.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{}/", segments.join("/"))
+        };
+        url.set_path(&path);
+
+        Ok(Self { base: url })
+    }
+
+    /// 规范化后的地址，去掉末尾的 `/`，用于保存和展示
+    pub fn normalized(&self) -> String {
+        self.base.as_str().trim_end_matches('/').to_string()
+    }
+
+    /// 是否使用 https
+    pub fn is_secure(&self) -> bool {
+        self.base.scheme() == "https"
+    }
+
+    /// WebSocket 消息流端点：`ws(s)://host[:port]/<子路径>/stream`
+    pub fn stream_url(&self) -> Result<Url, UrlError> {
+        let mut url = self.endpoint("stream")?;
+        let scheme = if self.is_secure() { "wss" } else { "ws" };
+        // http -> ws、https -> wss 之间总是可以互相转换
+        let _ = url.set_scheme(scheme);
+        Ok(url)
+    }
+
+    /// `url` 是否与服务器同源（协议、主机和端口都相同）
+    pub fn same_origin(&self, url: &Url) -> bool {
+        self.base.origin() == url.origin()
+    }
+
+    /// REST 端点，`path` 为相对路径，例如 `message?limit=100`
+    pub fn endpoint(&self, path: &str) -> Result<Url, UrlError> {
+        self.base
+            .join(path.trim_start_matches('/'))
+            .map_err(|e| UrlError::Invalid(format!("{}: {}", path, e)))
+    }
+}
+
+impl fmt::Display for ServerUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.normalized())
+    }
+}
+
+/// 没有写协议时推断默认协议
+fn default_scheme(input: &str) -> &'static str {
+    let authority = input.split(['/', '?', '#']).next().unwrap_or_default();
+
+    // 去掉端口，IPv6 字面量形如 [::1]:8080
+    let host = if authority.starts_with('[') {
+        authority.split(']').next().unwrap_or_default().trim_start_matches('[')
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+
+    let is_local = host.eq_ignore_ascii_case("localhost")
+        || host.parse::<std::net::IpAddr>().is_ok();
+
+    if is_local {
+        "http"
+    } else {
+        "https"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(input: &str) -> String {
+        ServerUrl::parse(input).unwrap().normalized()
+    }
+
+    #[test]
+    fn infers_scheme_from_host() {
+        assert_eq!(normalized("gotify.example.com"), "https://gotify.example.com");
+        assert_eq!(normalized("localhost:8080"), "http://localhost:8080");
+        assert_eq!(normalized("192.168.1.10"), "http://192.168.1.10");
+        assert_eq!(normalized("[::1]:8080"), "http://[::1]:8080");
+    }
+
+    #[test]
+    fn normalizes_scheme_and_path() {
+        assert_eq!(normalized("  wss://example.com//gotify///  "), "https://example.com/gotify");
+        assert_eq!(normalized("ws://example.com/"), "http://example.com");
+        assert_eq!(normalized("HTTPS://Example.COM:443/"), "https://example.com");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(ServerUrl::parse(" "), Err(UrlError::Empty));
+        assert_eq!(
+            ServerUrl::parse("ftp://example.com"),
+            Err(UrlError::UnsupportedScheme("ftp".to_string()))
+        );
+        assert_eq!(ServerUrl::parse("https://user:pw@example.com"), Err(UrlError::HasCredentials));
+        assert_eq!(ServerUrl::parse("https://example.com/?a=1"), Err(UrlError::HasQuery));
+        assert_eq!(ServerUrl::parse("https://example.com/#top"), Err(UrlError::HasFragment));
+        assert_eq!(ServerUrl::parse("https://example.com:0"), Err(UrlError::InvalidPort));
+        assert_eq!(ServerUrl::parse("https://example.com:70000"), Err(UrlError::InvalidPort));
+    }
+
+    #[test]
+    fn derives_endpoints_below_sub_path() {
+        let server = ServerUrl::parse("https://example.com/gotify").unwrap();
+        assert_eq!(server.stream_url().unwrap().as_str(), "wss://example.com/gotify/stream");
+        assert_eq!(
+            server.endpoint("/message?limit=10").unwrap().as_str(),
+            "https://example.com/gotify/message?limit=10"
+        );
+    }
+}
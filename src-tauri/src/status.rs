@@ -0,0 +1,148 @@
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+
+/// WebSocket 连接所处的状态
+#[derive(Clone, Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// 还没有启动过连接
+    #[default]
+    Idle,
+    /// 正在建立连接（TCP、TLS 和 WebSocket 握手），`attempt` 为 0 表示首次连接
+    Connecting { attempt: u32 },
+    /// 已连接，正在接收消息
+    Connected,
+    /// 连接断开，等待第 `attempt` 次重连；`next_retry_at` 为 Unix 毫秒时间戳
+    Backoff { attempt: u32, next_retry_at: u64 },
+    /// Token 无效或没有权限，已停止重连
+    AuthFailed,
+    /// 重连次数用尽或配置有误，已停止重连
+    Unreachable,
+    /// 用户主动停止
+    Stopped,
+}
+
+/// `get_connection_status` 返回、并随 `connection-state` 事件推送的连接状态
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct ConnectionStatus {
+    /// 所属服务器配置的 id
+    pub profile_id: String,
+    #[serde(flatten)]
+    pub state: ConnectionState,
+    /// 最近一次导致断开或连接失败的错误
+    pub last_error: Option<AppError>,
+    /// 本次连接建立的时间（Unix 毫秒时间戳），未连接时为 null
+    pub connected_since: Option<u64>,
+    /// 本次连接收到的消息数
+    pub session_messages: u64,
+    /// 应用启动以来收到的消息数
+    pub total_messages: u64,
+}
+
+/// 当前时间的 Unix 毫秒时间戳
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 保存单个服务器的连接状态，并在状态变化时通知前端
+pub struct StatusTracker {
+    inner: Mutex<ConnectionStatus>,
+}
+
+impl StatusTracker {
+    fn new(profile_id: &str) -> Self {
+        Self {
+            inner: Mutex::new(ConnectionStatus {
+                profile_id: profile_id.to_string(),
+                ..ConnectionStatus::default()
+            }),
+        }
+    }
+
+    pub fn snapshot(&self) -> ConnectionStatus {
+        self.inner.lock().unwrap().clone()
+    }
+
+    /// 切换到新状态并推送 `connection-state` 事件。`error` 为 None 时保留上一次的错误
+    pub fn transition<E: Emitter<tauri::Wry>>(
+        &self,
+        emitter: &E,
+        state: ConnectionState,
+        error: Option<AppError>,
+    ) {
+        let status = {
+            let mut status = self.inner.lock().unwrap();
+            if status.state == state && error.is_none() {
+                return;
+            }
+
+            if state == ConnectionState::Connected {
+                status.connected_since = Some(now_millis());
+                status.session_messages = 0;
+            } else {
+                status.connected_since = None;
+            }
+            if error.is_some() {
+                status.last_error = error;
+            }
+            status.state = state;
+            status.clone()
+        };
+
+        println!("🔀 连接状态: {:?}", status.state);
+        emitter.emit("connection-state", &status).ok();
+    }
+
+    /// 进入退避等待，计算下一次重连的时间
+    pub fn backoff<E: Emitter<tauri::Wry>>(
+        &self,
+        emitter: &E,
+        attempt: u32,
+        delay: Duration,
+        error: AppError,
+    ) {
+        let next_retry_at = now_millis() + delay.as_millis() as u64;
+        self.transition(emitter, ConnectionState::Backoff { attempt, next_retry_at }, Some(error));
+    }
+
+    /// 记录收到的一条消息
+    pub fn record_message(&self) {
+        let mut status = self.inner.lock().unwrap();
+        status.session_messages += 1;
+        status.total_messages += 1;
+    }
+}
+
+/// 所有服务器的连接状态，按配置 id 索引
+#[derive(Default)]
+pub struct StatusRegistry {
+    trackers: Mutex<HashMap<String, Arc<StatusTracker>>>,
+}
+
+impl StatusRegistry {
+    /// 取得服务器的状态，第一次访问时创建
+    pub fn tracker(&self, profile_id: &str) -> Arc<StatusTracker> {
+        self.trackers
+            .lock()
+            .unwrap()
+            .entry(profile_id.to_string())
+            .or_insert_with(|| Arc::new(StatusTracker::new(profile_id)))
+            .clone()
+    }
+
+    pub fn snapshot(&self, profile_id: &str) -> ConnectionStatus {
+        self.tracker(profile_id).snapshot()
+    }
+
+    /// 删除服务器配置后丢弃它的状态
+    pub fn remove(&self, profile_id: &str) {
+        self.trackers.lock().unwrap().remove(profile_id);
+    }
+}
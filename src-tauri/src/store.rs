@@ -0,0 +1,1229 @@
+use crate::extras::MessageExtras;
+use crate::retention::{PruneReport, RetentionPolicy};
+use crate::GotifyMessage;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+
+/// 单次查询最多返回的消息数
+const MAX_PAGE_LIMIT: u32 = 500;
+
+/// 没有指定数量时每页返回的消息数
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+
+/// 当前的数据库结构版本，保存在 `PRAGMA user_version` 中
+const SCHEMA_VERSION: i32 = 6;
+
+/// trigram 分词器能匹配的最短关键词（字符数），更短的关键词改用 LIKE 匹配
+const MIN_INDEXED_TERM_CHARS: usize = 3;
+
+#[derive(Debug)]
+pub enum StoreError {
+    /// 打开或初始化数据库失败
+    Open(String),
+    /// 执行 SQL 失败
+    Sql(rusqlite::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Open(e) => write!(f, "打开消息数据库失败: {}", e),
+            StoreError::Sql(e) => write!(f, "读写消息数据库失败: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sql(e)
+    }
+}
+
+/// 查询历史消息的筛选条件，所有条件同时满足
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct MessageFilter {
+    /// 只返回该服务器的消息
+    pub profile_id: Option<String>,
+    /// 只返回该应用的消息
+    pub appid: Option<u64>,
+    /// 最低优先级
+    pub min_priority: Option<u32>,
+    /// 最高优先级
+    pub max_priority: Option<u32>,
+    /// 只返回该时间（Unix 毫秒时间戳）及之后的消息
+    pub since: Option<i64>,
+    /// 只返回该时间（Unix 毫秒时间戳）之前的消息
+    pub until: Option<i64>,
+    /// 为 true 时只返回已读消息，为 false 时只返回未读消息
+    pub read: Option<bool>,
+}
+
+/// 一页历史消息，按时间从新到旧排列
+#[derive(Clone, Serialize, Debug)]
+pub struct MessagePage {
+    pub messages: Vec<GotifyMessage>,
+    /// 满足筛选条件的消息总数
+    pub total: u64,
+}
+
+/// 匹配到的关键词在文本中的位置，按 UTF-16 码元计算，方便前端直接截取字符串
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 一条搜索结果
+#[derive(Clone, Serialize, Debug)]
+pub struct SearchHit {
+    pub message: GotifyMessage,
+    /// 相关度，越小越相关（FTS5 bm25）；只有短关键词时为 0
+    pub score: f64,
+    pub title_highlights: Vec<Highlight>,
+    pub message_highlights: Vec<Highlight>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// 满足条件的结果总数
+    pub total: u64,
+}
+
+/// 单个应用保存的消息数
+#[derive(Clone, Serialize, Debug)]
+pub struct AppMessageCount {
+    pub profile_id: String,
+    pub appid: u64,
+    pub count: u64,
+}
+
+/// 单个服务器的未读消息数
+#[derive(Clone, Serialize, Debug)]
+pub struct ProfileUnreadCount {
+    pub profile_id: String,
+    pub count: u64,
+}
+
+/// 未读消息数，随 `unread-changed` 事件推送
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct UnreadCounts {
+    pub total: u64,
+    pub by_profile: Vec<ProfileUnreadCount>,
+    pub by_app: Vec<AppMessageCount>,
+}
+
+/// 消息历史的统计信息
+#[derive(Clone, Serialize, Debug)]
+pub struct StoreStats {
+    pub total: u64,
+    /// 标记为保留、不会被清理的消息数
+    pub kept: u64,
+    /// 最旧和最新消息的时间（Unix 毫秒时间戳）
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    pub per_app: Vec<AppMessageCount>,
+    /// 数据库文件占用的空间（字节）
+    pub size_bytes: u64,
+    /// 最近一次清理的结果
+    pub last_prune: Option<PruneReport>,
+}
+
+/// 本地的消息历史，保存在 `{config_dir}/.gotify_messages.db`
+pub struct MessageStore {
+    conn: Mutex<Connection>,
+    last_prune: Mutex<Option<PruneReport>>,
+}
+
+/// Gotify 的 RFC 3339 时间转换为 Unix 毫秒时间戳，用于跨时区排序
+fn timestamp_millis(date: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(date)
+        .ok()
+        .map(|date| date.timestamp_millis())
+}
+
+impl MessageStore {
+    pub fn open_default() -> Result<Self, StoreError> {
+        Self::open(&format!("{}/.gotify_messages.db", crate::get_config_dir()))
+    }
+
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let conn = Connection::open(path).map_err(|e| StoreError::Open(format!("{}: {}", path, e)))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&conn)?;
+        println!("✅ 消息数据库已打开: {}", path);
+        Ok(Self {
+            conn: Mutex::new(conn),
+            last_prune: Mutex::new(None),
+        })
+    }
+
+    /// 保存一条消息，已存在（同一服务器、同一 id）时忽略。返回是否为新消息
+    pub fn insert(&self, message: &GotifyMessage) -> Result<bool, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let received_at = crate::status::now_millis() as i64;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO messages
+                (profile_id, id, appid, title, message, priority, date, timestamp, received_at, extras, read,
+                 tags, highlight)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                message.profile_id,
+                message.id as i64,
+                message.appid as i64,
+                message.title,
+                message.message,
+                message.priority,
+                message.date,
+                timestamp_millis(&message.date).unwrap_or(received_at),
+                received_at,
+                message.extras.as_ref().map(MessageExtras::to_json),
+                message.read,
+                tags_json(&message.tags),
+                message.highlight,
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// 批量导入服务器上已有的历史消息，导入的消息视为已读，返回新增的条数
+    pub fn import(&self, messages: &[GotifyMessage]) -> Result<u64, StoreError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let received_at = crate::status::now_millis() as i64;
+        let mut inserted = 0;
+        {
+            let mut statement = tx.prepare(
+                "INSERT OR IGNORE INTO messages
+                    (profile_id, id, appid, title, message, priority, date, timestamp, received_at, extras, read,
+                     tags, highlight)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 1, ?11, ?12)",
+            )?;
+            for message in messages {
+                inserted += statement.execute(params![
+                    message.profile_id,
+                    message.id as i64,
+                    message.appid as i64,
+                    message.title,
+                    message.message,
+                    message.priority,
+                    message.date,
+                    timestamp_millis(&message.date).unwrap_or(received_at),
+                    received_at,
+                    message.extras.as_ref().map(MessageExtras::to_json),
+                    tags_json(&message.tags),
+                    message.highlight,
+                ])? as u64;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// 按时间从新到旧分页查询
+    pub fn query(
+        &self,
+        offset: u32,
+        limit: Option<u32>,
+        filter: &MessageFilter,
+    ) -> Result<MessagePage, StoreError> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        filter.push_conditions(&mut conditions, &mut values);
+        let where_clause = where_clause(&conditions);
+
+        let conn = self.conn.lock().unwrap();
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM messages m {}", where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(&format!(
+            "SELECT m.profile_id, m.id, m.appid, m.title, m.message, m.priority, m.date, m.keep, m.read, m.extras, m.tags, m.highlight
+             FROM messages m {}
+             ORDER BY m.timestamp DESC, m.id DESC
+             LIMIT {} OFFSET {}",
+            where_clause, limit, offset
+        ))?;
+        let messages = statement
+            .query_map(params_from_iter(values.iter()), row_to_message)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(MessagePage {
+            messages,
+            total: total as u64,
+        })
+    }
+
+    /// 按时间从旧到新返回满足条件的全部消息及其时间（Unix 毫秒时间戳），用于导出
+    pub fn all_messages(&self, filter: &MessageFilter) -> Result<Vec<(GotifyMessage, i64)>, StoreError> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        filter.push_conditions(&mut conditions, &mut values);
+
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(&format!(
+            "SELECT m.profile_id, m.id, m.appid, m.title, m.message, m.priority, m.date, m.keep, m.read, m.extras, m.tags, m.highlight, m.timestamp
+             FROM messages m {}
+             ORDER BY m.timestamp ASC, m.id ASC",
+            where_clause(&conditions)
+        ))?;
+        let messages = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok((row_to_message(row)?, row.get::<_, i64>(12)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(messages)
+    }
+
+    /// 全文搜索标题和正文，结果按相关度排序，相关度相同时新消息在前。
+    ///
+    /// 关键词以空白分隔，全部匹配才算命中，不区分大小写；用双引号括起的内容作为一个整体匹配。
+    pub fn search(
+        &self,
+        query: &str,
+        offset: u32,
+        limit: Option<u32>,
+        filter: &MessageFilter,
+    ) -> Result<SearchResults, StoreError> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+        let terms = parse_terms(query);
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        // 足够长的关键词交给 FTS5 索引，过短的关键词 trigram 无法匹配，退回 LIKE
+        let (indexed, short): (Vec<&String>, Vec<&String>) = terms
+            .iter()
+            .partition(|term| term.chars().count() >= MIN_INDEXED_TERM_CHARS);
+        let use_index = !indexed.is_empty();
+        if use_index {
+            conditions.push("messages_fts MATCH ?".to_string());
+            let expression = indexed
+                .iter()
+                .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            values.push(expression.into());
+        }
+        for term in short {
+            conditions.push("(m.title LIKE ? ESCAPE '\\' OR m.message LIKE ? ESCAPE '\\')".to_string());
+            let pattern = format!("%{}%", escape_like(term));
+            values.push(pattern.clone().into());
+            values.push(pattern.into());
+        }
+        filter.push_conditions(&mut conditions, &mut values);
+        let where_clause = where_clause(&conditions);
+
+        let (from, score, order) = if use_index {
+            (
+                "messages_fts JOIN messages m ON m.rowid = messages_fts.rowid",
+                // 标题命中比正文命中更相关
+                "bm25(messages_fts, 10.0, 1.0)",
+                "score, m.timestamp DESC",
+            )
+        } else {
+            ("messages m", "0.0", "m.timestamp DESC, m.id DESC")
+        };
+
+        let conn = self.conn.lock().unwrap();
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} {}", from, where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(&format!(
+            "SELECT m.profile_id, m.id, m.appid, m.title, m.message, m.priority, m.date, m.keep, m.read, m.extras, m.tags, m.highlight, {} AS score
+             FROM {} {}
+             ORDER BY {}
+             LIMIT {} OFFSET {}",
+            score, from, where_clause, order, limit, offset
+        ))?;
+        let hits = statement
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok((row_to_message(row)?, row.get::<_, f64>(12)?))
+            })?
+            .map(|row| {
+                row.map(|(message, score)| SearchHit {
+                    title_highlights: highlights(&message.title, &terms),
+                    message_highlights: highlights(&message.message, &terms),
+                    message,
+                    score,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SearchResults {
+            hits,
+            total: total as u64,
+        })
+    }
+
+    /// 设置消息的保留标记，返回消息是否存在
+    pub fn set_keep(&self, profile_id: &str, id: u64, keep: bool) -> Result<bool, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE messages SET keep = ?1 WHERE profile_id = ?2 AND id = ?3",
+            params![keep, profile_id, id as i64],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// 把指定消息标记为已读或未读，返回状态发生变化的消息数
+    pub fn mark_read(&self, profile_id: &str, ids: &[u64], read: bool) -> Result<u64, StoreError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut changed = 0;
+        {
+            let mut statement =
+                tx.prepare("UPDATE messages SET read = ?1 WHERE profile_id = ?2 AND id = ?3 AND read != ?1")?;
+            for id in ids {
+                changed += statement.execute(params![read, profile_id, *id as i64])? as u64;
+            }
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// 把所有未读消息标记为已读，可以只限于某个服务器或应用，返回标记的消息数
+    pub fn mark_all_read(&self, profile_id: Option<&str>, appid: Option<u64>) -> Result<u64, StoreError> {
+        let filter = MessageFilter {
+            profile_id: profile_id.map(str::to_string),
+            appid,
+            read: Some(false),
+            ..MessageFilter::default()
+        };
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        filter.push_conditions(&mut conditions, &mut values);
+
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            &format!("UPDATE messages AS m SET read = 1 {}", where_clause(&conditions)),
+            params_from_iter(values.iter()),
+        )?;
+        Ok(changed as u64)
+    }
+
+    /// 按服务器和应用统计未读消息数
+    pub fn unread_counts(&self) -> Result<UnreadCounts, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT profile_id, appid, COUNT(*) FROM messages WHERE read = 0
+             GROUP BY profile_id, appid ORDER BY profile_id, appid",
+        )?;
+        let by_app = statement
+            .query_map([], |row| {
+                Ok(AppMessageCount {
+                    profile_id: row.get(0)?,
+                    appid: row.get::<_, i64>(1)? as u64,
+                    count: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut counts = UnreadCounts::default();
+        for app in &by_app {
+            counts.total += app.count;
+            match counts.by_profile.last_mut() {
+                Some(last) if last.profile_id == app.profile_id => last.count += app.count,
+                _ => counts.by_profile.push(ProfileUnreadCount {
+                    profile_id: app.profile_id.clone(),
+                    count: app.count,
+                }),
+            }
+        }
+        counts.by_app = by_app;
+        Ok(counts)
+    }
+
+    /// 删除一条消息，返回消息是否存在
+    pub fn delete(&self, profile_id: &str, id: u64) -> Result<bool, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM messages WHERE profile_id = ?1 AND id = ?2",
+            params![profile_id, id as i64],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// 删除某个服务器上的消息，指定 `appid` 时只删除该应用的消息，返回删除的条数
+    pub fn delete_all(&self, profile_id: &str, appid: Option<u64>) -> Result<u64, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = match appid {
+            Some(appid) => conn.execute(
+                "DELETE FROM messages WHERE profile_id = ?1 AND appid = ?2",
+                params![profile_id, appid as i64],
+            )?,
+            None => conn.execute("DELETE FROM messages WHERE profile_id = ?1", params![profile_id])?,
+        };
+        Ok(deleted as u64)
+    }
+
+    /// 按保留策略删除消息，标记为保留的消息不会被删除
+    pub fn prune(&self, policy: &RetentionPolicy) -> Result<PruneReport, StoreError> {
+        let now = crate::status::now_millis();
+        let cutoff = |days: u32| now as i64 - days as i64 * 24 * 60 * 60 * 1000;
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut report = PruneReport::default();
+
+        // 应用规则：各自的天数和条数
+        for rule in &policy.apps {
+            let mut scope = vec!["appid = ?".to_string()];
+            let mut values: Vec<Value> = vec![(rule.appid as i64).into()];
+            if let Some(profile_id) = &rule.profile_id {
+                scope.push("profile_id = ?".to_string());
+                values.push(profile_id.clone().into());
+            }
+            let scope = scope.join(" AND ");
+
+            if let Some(days) = rule.max_age_days {
+                let mut params = values.clone();
+                params.push(cutoff(days).into());
+                report.by_app += tx.execute(
+                    &format!("DELETE FROM messages WHERE keep = 0 AND {} AND timestamp < ?", scope),
+                    params_from_iter(params.iter()),
+                )? as u64;
+            }
+            if let Some(max_count) = rule.max_count {
+                report.by_app += delete_beyond(&tx, &scope, &values, max_count)?;
+            }
+        }
+
+        // 全局天数，单独设置了天数的应用除外
+        if let Some(days) = policy.max_age_days {
+            let mut conditions = vec!["keep = 0".to_string(), "timestamp < ?".to_string()];
+            let mut values: Vec<Value> = vec![cutoff(days).into()];
+            for rule in policy.apps.iter().filter(|rule| rule.max_age_days.is_some()) {
+                match &rule.profile_id {
+                    Some(profile_id) => {
+                        conditions.push("NOT (appid = ? AND profile_id = ?)".to_string());
+                        values.push((rule.appid as i64).into());
+                        values.push(profile_id.clone().into());
+                    }
+                    None => {
+                        conditions.push("appid != ?".to_string());
+                        values.push((rule.appid as i64).into());
+                    }
+                }
+            }
+            report.by_age = tx.execute(
+                &format!("DELETE FROM messages WHERE {}", conditions.join(" AND ")),
+                params_from_iter(values.iter()),
+            )? as u64;
+        }
+
+        // 全局总数
+        if let Some(max_messages) = policy.max_messages {
+            report.by_count = delete_beyond(&tx, "1 = 1", &[], max_messages)?;
+        }
+
+        tx.commit()?;
+        report.finished_at = crate::status::now_millis();
+        *self.last_prune.lock().unwrap() = Some(report.clone());
+        Ok(report)
+    }
+
+    pub fn stats(&self) -> Result<StoreStats, StoreError> {
+        let conn = self.conn.lock().unwrap();
+
+        let (total, kept, oldest, newest) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(keep), 0), MIN(timestamp), MAX(timestamp) FROM messages",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            },
+        )?;
+
+        let mut statement = conn.prepare(
+            "SELECT profile_id, appid, COUNT(*) AS count FROM messages
+             GROUP BY profile_id, appid ORDER BY count DESC",
+        )?;
+        let per_app = statement
+            .query_map([], |row| {
+                Ok(AppMessageCount {
+                    profile_id: row.get(0)?,
+                    appid: row.get::<_, i64>(1)? as u64,
+                    count: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let size_bytes: i64 = conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(StoreStats {
+            total,
+            kept,
+            oldest,
+            newest,
+            per_app,
+            size_bytes: size_bytes as u64,
+            last_prune: self.last_prune.lock().unwrap().clone(),
+        })
+    }
+}
+
+/// 在 `scope` 范围内只保留最新的 `max_count` 条未标记保留的消息，返回删除的条数
+fn delete_beyond(
+    conn: &Connection,
+    scope: &str,
+    values: &[Value],
+    max_count: u64,
+) -> Result<u64, StoreError> {
+    let mut params = values.to_vec();
+    params.extend(values.iter().cloned());
+    params.push((max_count as i64).into());
+
+    let deleted = conn.execute(
+        &format!(
+            "DELETE FROM messages WHERE keep = 0 AND {scope} AND rowid NOT IN (
+                SELECT rowid FROM messages WHERE keep = 0 AND {scope}
+                ORDER BY timestamp DESC, id DESC LIMIT ?
+            )"
+        ),
+        params_from_iter(params.iter()),
+    )?;
+    Ok(deleted as u64)
+}
+
+impl MessageFilter {
+    fn push_conditions(&self, conditions: &mut Vec<String>, values: &mut Vec<Value>) {
+        if let Some(profile_id) = &self.profile_id {
+            conditions.push("m.profile_id = ?".to_string());
+            values.push(profile_id.clone().into());
+        }
+        if let Some(appid) = self.appid {
+            conditions.push("m.appid = ?".to_string());
+            values.push((appid as i64).into());
+        }
+        if let Some(min_priority) = self.min_priority {
+            conditions.push("m.priority >= ?".to_string());
+            values.push(min_priority.into());
+        }
+        if let Some(max_priority) = self.max_priority {
+            conditions.push("m.priority <= ?".to_string());
+            values.push(max_priority.into());
+        }
+        if let Some(since) = self.since {
+            conditions.push("m.timestamp >= ?".to_string());
+            values.push(since.into());
+        }
+        if let Some(until) = self.until {
+            conditions.push("m.timestamp < ?".to_string());
+            values.push(until.into());
+        }
+        if let Some(read) = self.read {
+            conditions.push("m.read = ?".to_string());
+            values.push(read.into());
+        }
+    }
+}
+
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// 拆分搜索关键词：以空白分隔，双引号内的内容作为一个关键词
+fn parse_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                if !quoted && !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        terms.push(current);
+    }
+
+    terms.iter_mut().for_each(|term| *term = term.to_lowercase());
+    terms.retain(|term| !term.trim().is_empty());
+    terms.dedup();
+    terms
+}
+
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 找出关键词在文本中的所有位置（不区分大小写），重叠的位置会合并
+fn highlights(text: &str, terms: &[String]) -> Vec<Highlight> {
+    // 逐字符转成小写后比较，同时记录每个字符的 UTF-16 起始位置
+    let mut chars: Vec<char> = Vec::new();
+    let mut offsets: Vec<usize> = Vec::new();
+    let mut utf16 = 0;
+    for c in text.chars() {
+        chars.push(c.to_lowercase().next().unwrap_or(c));
+        offsets.push(utf16);
+        utf16 += c.len_utf16();
+    }
+    offsets.push(utf16);
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let needle: Vec<char> = term.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+        if needle.is_empty() || needle.len() > chars.len() {
+            continue;
+        }
+        for start in 0..=chars.len() - needle.len() {
+            if chars[start..start + needle.len()] == needle[..] {
+                ranges.push((start, start + needle.len()));
+            }
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(start, end)| Highlight {
+            start: offsets[start],
+            end: offsets[end],
+        })
+        .collect()
+}
+
+fn row_to_message(row: &rusqlite::Row<'_>) -> rusqlite::Result<GotifyMessage> {
+    Ok(GotifyMessage {
+        profile_id: row.get(0)?,
+        id: row.get::<_, i64>(1)? as u64,
+        appid: row.get::<_, i64>(2)? as u64,
+        title: row.get(3)?,
+        message: row.get(4)?,
+        priority: row.get(5)?,
+        date: row.get(6)?,
+        keep: row.get(7)?,
+        read: row.get(8)?,
+        extras: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|extras| serde_json::from_str(&extras).ok()),
+        app_name: None,
+        app_icon: None,
+        tags: row
+            .get::<_, Option<String>>(10)?
+            .and_then(|tags| serde_json::from_str(&tags).ok())
+            .unwrap_or_default(),
+        highlight: row.get(11)?,
+    })
+}
+
+/// 标签保存为 JSON 数组，没有标签时为 NULL
+fn tags_json(tags: &[String]) -> Option<String> {
+    (!tags.is_empty()).then(|| serde_json::to_string(tags).unwrap())
+}
+
+/// 各版本的升级语句，第 n 项把数据库从版本 n 升级到 n + 1
+const MIGRATIONS: [&str; SCHEMA_VERSION as usize] = [
+    "CREATE TABLE IF NOT EXISTS messages (
+        profile_id  TEXT    NOT NULL,
+        id          INTEGER NOT NULL,
+        appid       INTEGER NOT NULL,
+        title       TEXT    NOT NULL,
+        message     TEXT    NOT NULL,
+        priority    INTEGER NOT NULL,
+        date        TEXT    NOT NULL,
+        timestamp   INTEGER NOT NULL,
+        received_at INTEGER NOT NULL,
+        PRIMARY KEY (profile_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS messages_timestamp ON messages (timestamp DESC);",
+    // 标题和正文的全文索引。trigram 分词器按子串匹配，中文不需要分词也能搜索
+    "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+        title, message,
+        content = 'messages', content_rowid = 'rowid',
+        tokenize = 'trigram'
+    );
+    CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+        INSERT INTO messages_fts (rowid, title, message) VALUES (new.rowid, new.title, new.message);
+    END;
+    CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, title, message)
+        VALUES ('delete', old.rowid, old.title, old.message);
+    END;
+    CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF title, message ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, title, message)
+        VALUES ('delete', old.rowid, old.title, old.message);
+        INSERT INTO messages_fts (rowid, title, message) VALUES (new.rowid, new.title, new.message);
+    END;
+    INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');",
+    // 标记为保留的消息不会被保留策略清理
+    "ALTER TABLE messages ADD COLUMN keep INTEGER NOT NULL DEFAULT 0;",
+    // 升级前保存的消息都已经在界面上显示过，视为已读
+    "ALTER TABLE messages ADD COLUMN read INTEGER NOT NULL DEFAULT 0;
+    UPDATE messages SET read = 1;
+    CREATE INDEX IF NOT EXISTS messages_unread ON messages (profile_id, appid) WHERE read = 0;",
+    // 消息的 extras，保存为 JSON
+    "ALTER TABLE messages ADD COLUMN extras TEXT;",
+    // 过滤规则添加的标签（JSON 数组）和高亮颜色
+    "ALTER TABLE messages ADD COLUMN tags TEXT;
+    ALTER TABLE messages ADD COLUMN highlight TEXT;",
+];
+
+/// 按 `user_version` 逐步升级数据库结构。
+///
+/// 每一步和对应的版本号在同一个事务中提交，中途退出时下次启动会从未完成的那一步重新开始
+fn migrate(conn: &Connection) -> Result<(), StoreError> {
+    let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (from, sql) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", from as i32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retention::AppRetention;
+
+    fn open() -> MessageStore {
+        MessageStore::open(":memory:").unwrap()
+    }
+
+    fn message(profile_id: &str, id: u64, title: &str, date: &str) -> GotifyMessage {
+        GotifyMessage {
+            id,
+            appid: 1,
+            message: String::new(),
+            title: title.to_string(),
+            priority: 5,
+            date: date.to_string(),
+            extras: None,
+            profile_id: profile_id.to_string(),
+            keep: false,
+            read: false,
+            app_name: None,
+            app_icon: None,
+            tags: Vec::new(),
+            highlight: None,
+        }
+    }
+
+    /// 第 `minute` 分钟的消息，用于按时间排序的测试
+    fn at_minute(profile_id: &str, id: u64, minute: u32) -> GotifyMessage {
+        message(profile_id, id, &format!("消息 {}", id), &format!("2024-01-01T08:{:02}:00+08:00", minute))
+    }
+
+    fn user_version(conn: &Connection) -> i32 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    fn ids(messages: &[GotifyMessage]) -> Vec<u64> {
+        messages.iter().map(|message| message.id).collect()
+    }
+
+    #[test]
+    fn migrates_old_database_step_by_step() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO messages (profile_id, id, appid, title, message, priority, date, timestamp, received_at)
+             VALUES ('home', 1, 2, '旧消息', 'backup done', 5, '2024-01-01T00:00:00Z', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(user_version(&conn), SCHEMA_VERSION);
+
+        // 升级前的消息视为已读，并且补建了全文索引
+        let (keep, read): (bool, bool) = conn
+            .query_row("SELECT keep, read FROM messages", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((keep, read), (false, true));
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM messages_fts WHERE messages_fts MATCH 'backup'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(indexed, 1);
+
+        // 已是最新版本时不再执行任何步骤
+        migrate(&conn).unwrap();
+        assert_eq!(user_version(&conn), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn failed_migration_step_keeps_previous_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in &MIGRATIONS[..2] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        // 第 3 步要添加的列已经存在，这一步会失败
+        conn.execute_batch("ALTER TABLE messages ADD COLUMN keep INTEGER NOT NULL DEFAULT 0;")
+            .unwrap();
+
+        assert!(migrate(&conn).is_err());
+        assert_eq!(user_version(&conn), 2);
+    }
+
+    #[test]
+    fn ignores_duplicate_messages_per_profile() {
+        let store = open();
+        assert!(store.insert(&at_minute("home", 1, 0)).unwrap());
+        assert!(!store.insert(&at_minute("home", 1, 5)).unwrap());
+        assert!(store.insert(&at_minute("office", 1, 0)).unwrap());
+
+        let page = store.query(0, None, &MessageFilter::default()).unwrap();
+        assert_eq!(page.total, 2);
+        // 重复的消息不会覆盖已保存的内容
+        let home = page.messages.iter().find(|message| message.profile_id == "home").unwrap();
+        assert_eq!(home.date, "2024-01-01T08:00:00+08:00");
+    }
+
+    #[test]
+    fn round_trips_message_fields() {
+        let store = open();
+        let mut saved = at_minute("home", 7, 0);
+        saved.read = true;
+        saved.tags = vec!["备份".to_string()];
+        saved.highlight = Some("#ff0000".to_string());
+        saved.extras = Some(serde_json::from_str(r#"{"client::display": {"contentType": "text/markdown"}}"#).unwrap());
+        store.insert(&saved).unwrap();
+
+        let loaded = store.query(0, None, &MessageFilter::default()).unwrap().messages.remove(0);
+        assert!(loaded.read);
+        assert_eq!(loaded.tags, ["备份"]);
+        assert_eq!(loaded.highlight.as_deref(), Some("#ff0000"));
+        assert!(loaded.extras.is_some_and(|extras| extras.is_markdown()));
+    }
+
+    #[test]
+    fn imports_messages_as_read() {
+        let store = open();
+        store.insert(&at_minute("home", 1, 0)).unwrap();
+        let mut tagged = at_minute("home", 2, 1);
+        tagged.tags = vec!["导入".to_string()];
+        tagged.highlight = Some("#00ff00".to_string());
+
+        assert_eq!(store.import(&[at_minute("home", 1, 0), tagged]).unwrap(), 1);
+
+        let filter = MessageFilter {
+            read: Some(true),
+            ..MessageFilter::default()
+        };
+        let imported = store.query(0, None, &filter).unwrap().messages;
+        assert_eq!(ids(&imported), [2]);
+        assert_eq!(imported[0].tags, ["导入"]);
+        assert_eq!(imported[0].highlight.as_deref(), Some("#00ff00"));
+        // 原有的未读消息保持未读
+        assert_eq!(store.unread_counts().unwrap().total, 1);
+    }
+
+    #[test]
+    fn pages_newest_first() {
+        let store = open();
+        for (id, minute) in [(1, 0), (2, 10), (3, 20), (4, 30), (5, 40)] {
+            store.insert(&at_minute("home", id, minute)).unwrap();
+        }
+        // 时间相同时 id 大的在前
+        store.insert(&at_minute("office", 9, 40)).unwrap();
+
+        let filter = MessageFilter {
+            profile_id: Some("home".to_string()),
+            ..MessageFilter::default()
+        };
+        let first = store.query(0, Some(2), &filter).unwrap();
+        assert_eq!(first.total, 5);
+        assert_eq!(ids(&first.messages), [5, 4]);
+        assert_eq!(ids(&store.query(2, Some(2), &filter).unwrap().messages), [3, 2]);
+        assert_eq!(ids(&store.query(4, Some(2), &filter).unwrap().messages), [1]);
+
+        let all = store.query(0, None, &MessageFilter::default()).unwrap();
+        assert_eq!(ids(&all.messages), [9, 5, 4, 3, 2, 1]);
+
+        // 时间按实际时刻比较，与时区写法无关
+        let since = MessageFilter {
+            since: timestamp_millis("2024-01-01T00:20:00Z"),
+            ..filter
+        };
+        assert_eq!(ids(&store.query(0, None, &since).unwrap().messages), [5, 4, 3]);
+    }
+
+    fn search_store() -> MessageStore {
+        let store = open();
+        let messages = [
+            (1, "数据库备份完成", "耗时 3 分钟"),
+            (2, "磁盘告警", "Disk FULL on /var，已用 95%"),
+            (3, "🎉 发布 v1.2", "release notes: backup 已修复"),
+            (4, "disk check", "all good"),
+        ];
+        for (id, title, body) in messages {
+            let mut message = at_minute("home", id, id as u32);
+            message.title = title.to_string();
+            message.message = body.to_string();
+            store.insert(&message).unwrap();
+        }
+        store
+    }
+
+    fn search(store: &MessageStore, query: &str) -> SearchResults {
+        store.search(query, 0, None, &MessageFilter::default()).unwrap()
+    }
+
+    fn hit_ids(results: &SearchResults) -> Vec<u64> {
+        results.hits.iter().map(|hit| hit.message.id).collect()
+    }
+
+    fn ranges(highlights: &[Highlight]) -> Vec<(usize, usize)> {
+        highlights.iter().map(|h| (h.start, h.end)).collect()
+    }
+
+    #[test]
+    fn splits_terms_and_keeps_quoted_phrases() {
+        assert_eq!(parse_terms(r#" 备份  "Disk FULL" ok ok"#), ["备份", "disk full", "ok"]);
+        assert_eq!(parse_terms(r#""" "  " "#), Vec::<String>::new());
+    }
+
+    #[test]
+    fn highlights_use_utf16_offsets() {
+        let terms = vec!["backup".to_string(), "发布".to_string()];
+        // 🎉 占 2 个 UTF-16 码元
+        assert_eq!(ranges(&highlights("🎉 发布 BackUp", &terms)), [(3, 5), (6, 12)]);
+        assert_eq!(ranges(&highlights("🎉", &["🎉".to_string()])), [(0, 2)]);
+        assert!(highlights("", &terms).is_empty());
+    }
+
+    #[test]
+    fn merges_overlapping_highlights() {
+        assert_eq!(ranges(&highlights("aaaa", &["aa".to_string()])), [(0, 4)]);
+        let terms = vec!["abc".to_string(), "cde".to_string()];
+        assert_eq!(ranges(&highlights("xabcdefabc", &terms)), [(1, 6), (7, 10)]);
+    }
+
+    #[test]
+    fn searches_with_trigram_index() {
+        let store = search_store();
+
+        let results = search(&store, "备份完成");
+        assert_eq!(hit_ids(&results), [1]);
+        assert_eq!(ranges(&results.hits[0].title_highlights), [(3, 7)]);
+        assert!(results.hits[0].score < 0.0);
+
+        // 多个关键词必须全部匹配，不区分大小写
+        let results = search(&store, "disk full");
+        assert_eq!(hit_ids(&results), [2]);
+        assert_eq!(ranges(&results.hits[0].message_highlights), [(0, 4), (5, 9)]);
+
+        // 标题命中比正文命中更相关
+        let results = search(&store, "backup");
+        assert_eq!(results.total, 1);
+        let results = search(&store, "disk");
+        assert_eq!(hit_ids(&results), [4, 2]);
+    }
+
+    #[test]
+    fn falls_back_to_like_for_short_terms() {
+        let store = search_store();
+
+        let results = search(&store, "备份");
+        assert_eq!(hit_ids(&results), [1]);
+        assert_eq!(results.hits[0].score, 0.0);
+        assert_eq!(ranges(&results.hits[0].title_highlights), [(3, 5)]);
+
+        let results = search(&store, "🎉");
+        assert_eq!(hit_ids(&results), [3]);
+        assert_eq!(ranges(&results.hits[0].title_highlights), [(0, 2)]);
+
+        // LIKE 的通配符按普通字符匹配
+        assert_eq!(hit_ids(&search(&store, "5%")), [2]);
+        assert!(search(&store, "_").hits.is_empty());
+
+        // 长短关键词混合时同时使用索引和 LIKE，新消息在前
+        assert_eq!(hit_ids(&search(&store, "已 release")), [3]);
+        assert_eq!(hit_ids(&search(&store, "已")), [3, 2]);
+    }
+
+    #[test]
+    fn search_respects_filters_and_paging() {
+        let store = search_store();
+        let mut other = at_minute("office", 1, 59);
+        other.title = "备份失败".to_string();
+        store.insert(&other).unwrap();
+
+        assert_eq!(search(&store, "备份").total, 2);
+        let filter = MessageFilter {
+            profile_id: Some("office".to_string()),
+            ..MessageFilter::default()
+        };
+        let results = store.search("备份", 0, None, &filter).unwrap();
+        assert_eq!(hit_ids(&results), [1]);
+        assert_eq!(results.hits[0].message.profile_id, "office");
+
+        let second = store.search("备份", 1, Some(1), &MessageFilter::default()).unwrap();
+        assert_eq!(second.total, 2);
+        assert_eq!(second.hits.len(), 1);
+        assert_eq!(second.hits[0].message.profile_id, "home");
+    }
+
+    /// `days` 天前收到的消息
+    fn days_ago(profile_id: &str, id: u64, appid: u64, days: i64) -> GotifyMessage {
+        let date = chrono::Utc::now() - chrono::Duration::days(days) - chrono::Duration::minutes(id as i64);
+        let mut message = message(profile_id, id, "", &date.to_rfc3339());
+        message.appid = appid;
+        message
+    }
+
+    fn store_with(messages: &[GotifyMessage]) -> MessageStore {
+        let store = open();
+        for message in messages {
+            store.insert(message).unwrap();
+        }
+        store
+    }
+
+    fn remaining(store: &MessageStore) -> Vec<u64> {
+        let mut ids = ids(&store.query(0, None, &MessageFilter::default()).unwrap().messages);
+        ids.sort_unstable();
+        ids
+    }
+
+    fn app_rule(profile_id: Option<&str>, appid: u64, max_age_days: Option<u32>, max_count: Option<u64>) -> AppRetention {
+        AppRetention {
+            profile_id: profile_id.map(str::to_string),
+            appid,
+            max_age_days,
+            max_count,
+        }
+    }
+
+    #[test]
+    fn default_policy_keeps_everything() {
+        let store = store_with(&[days_ago("home", 1, 1, 400), days_ago("home", 2, 1, 0)]);
+        let report = store.prune(&RetentionPolicy::default()).unwrap();
+        assert_eq!(report.total(), 0);
+        assert_eq!(remaining(&store), [1, 2]);
+    }
+
+    #[test]
+    fn prunes_by_global_age_except_kept() {
+        let store = store_with(&[days_ago("home", 1, 1, 10), days_ago("home", 2, 1, 10), days_ago("home", 3, 1, 1)]);
+        store.set_keep("home", 2, true).unwrap();
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(7),
+            ..RetentionPolicy::default()
+        };
+        let report = store.prune(&policy).unwrap();
+        assert_eq!((report.by_age, report.total()), (1, 1));
+        assert_eq!(remaining(&store), [2, 3]);
+        assert_eq!(store.stats().unwrap().last_prune.unwrap().by_age, 1);
+    }
+
+    #[test]
+    fn app_age_overrides_global_age() {
+        let store = store_with(&[
+            days_ago("home", 1, 2, 10),
+            days_ago("home", 2, 2, 40),
+            days_ago("office", 3, 2, 10),
+            days_ago("home", 4, 1, 10),
+        ]);
+        let policy = RetentionPolicy {
+            max_age_days: Some(7),
+            apps: vec![app_rule(Some("home"), 2, Some(30), None)],
+            ..RetentionPolicy::default()
+        };
+
+        let report = store.prune(&policy).unwrap();
+        // 只对 home 生效的规则不影响 office 上 id 相同的应用
+        assert_eq!((report.by_app, report.by_age), (1, 2));
+        assert_eq!(remaining(&store), [1]);
+
+        // 对所有服务器生效的规则
+        let store = store_with(&[days_ago("home", 1, 2, 10), days_ago("office", 3, 2, 10)]);
+        let policy = RetentionPolicy {
+            max_age_days: Some(7),
+            apps: vec![app_rule(None, 2, Some(30), None)],
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(store.prune(&policy).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn caps_counts_per_app_and_globally() {
+        let store = store_with(&[
+            days_ago("home", 1, 2, 4),
+            days_ago("home", 2, 2, 3),
+            days_ago("home", 3, 2, 2),
+            days_ago("home", 4, 2, 1),
+            days_ago("home", 5, 1, 5),
+            days_ago("home", 6, 1, 0),
+        ]);
+        // 保留的消息既不会被删除，也不占用条数
+        store.set_keep("home", 1, true).unwrap();
+
+        let policy = RetentionPolicy {
+            apps: vec![app_rule(None, 2, None, Some(2))],
+            ..RetentionPolicy::default()
+        };
+        let report = store.prune(&policy).unwrap();
+        assert_eq!(report.by_app, 1);
+        assert_eq!(remaining(&store), [1, 3, 4, 5, 6]);
+
+        let policy = RetentionPolicy {
+            max_messages: Some(2),
+            apps: vec![app_rule(None, 2, None, Some(2))],
+            ..RetentionPolicy::default()
+        };
+        let report = store.prune(&policy).unwrap();
+        assert_eq!((report.by_app, report.by_count), (0, 2));
+        assert_eq!(remaining(&store), [1, 4, 6]);
+    }
+}
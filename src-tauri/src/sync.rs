@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 重连后补拉断线期间消息的策略
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CatchUpPolicy {
+    /// 是否在每次（重新）连接后补拉消息
+    pub enabled: bool,
+    /// 单次最多补拉的消息数量
+    pub max_messages: usize,
+    /// 补拉的消息超过该数量时只发送一条汇总通知，0 表示总是汇总
+    pub summary_threshold: usize,
+}
+
+impl Default for CatchUpPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_messages: 500,
+            summary_threshold: 3,
+        }
+    }
+}
+
+/// 首次连接时从服务器导入历史消息的策略
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ImportPolicy {
+    /// 首次连接时是否导入服务器上已有的消息
+    pub enabled: bool,
+    /// 最多导入的消息数量
+    pub max_messages: usize,
+    /// 只导入最近若干天的消息，为空表示不限制
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for ImportPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_messages: 1000,
+            max_age_days: Some(30),
+        }
+    }
+}
+
+/// 持久化的同步进度：已处理过的最大消息 id
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SyncState {
+    pub last_message_id: Option<u64>,
+}
+
+/// 多个连接任务会同时更新进度文件，读写时需要串行
+static SYNC_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+fn sync_state_path() -> String {
+    format!("{}/.gotify_sync_state.json", crate::get_config_dir())
+}
+
+/// 读取所有服务器的同步进度，按配置 id 索引
+fn load_all() -> HashMap<String, SyncState> {
+    let Ok(content) = std::fs::read_to_string(sync_state_path()) else {
+        return HashMap::new();
+    };
+
+    if let Ok(states) = serde_json::from_str::<HashMap<String, SyncState>>(&content) {
+        return states;
+    }
+    // 旧版文件只保存了单个服务器的进度，归到迁移后的默认配置下
+    match serde_json::from_str::<SyncState>(&content) {
+        Ok(state) => HashMap::from([(crate::profiles::DEFAULT_PROFILE_ID.to_string(), state)]),
+        Err(_) => HashMap::new(),
+    }
+}
+
+fn save_all(states: &HashMap<String, SyncState>) {
+    let path = sync_state_path();
+    if let Err(e) = std::fs::write(&path, serde_json::to_string_pretty(states).unwrap()) {
+        eprintln!("❌ 保存同步进度失败: {}", e);
+    }
+}
+
+pub fn load_sync_state(profile_id: &str) -> SyncState {
+    let _guard = SYNC_FILE_LOCK.lock().unwrap();
+    load_all().remove(profile_id).unwrap_or_default()
+}
+
+pub fn save_sync_state(profile_id: &str, state: &SyncState) {
+    let _guard = SYNC_FILE_LOCK.lock().unwrap();
+    let mut states = load_all();
+    states.insert(profile_id.to_string(), state.clone());
+    save_all(&states);
+}
+
+/// 删除服务器配置时一并清除它的同步进度
+pub fn remove_sync_state(profile_id: &str) {
+    let _guard = SYNC_FILE_LOCK.lock().unwrap();
+    let mut states = load_all();
+    if states.remove(profile_id).is_some() {
+        save_all(&states);
+    }
+}
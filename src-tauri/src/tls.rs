@@ -0,0 +1,429 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use tokio::net::TcpStream;
+
+/// 单个连接的 TLS 选项，用于内部 CA 或自签名证书的服务器
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TlsOptions {
+    /// 额外信任的 CA 证书（PEM 格式，可以包含多张证书）
+    pub ca_bundle_path: Option<String>,
+    /// 证书固定：整张证书的 SHA-256 十六进制指纹，或 `sha256/<base64>` 形式的公钥（SPKI）指纹。
+    /// 设置后以指纹作为唯一的信任依据，不再校验证书链和主机名
+    pub pin_sha256: Option<String>,
+    /// 接受任何证书，仅用于开发调试
+    pub accept_invalid_certs: bool,
+    /// 双向 TLS（mTLS）使用的客户端证书
+    pub client_identity: Option<ClientIdentity>,
+}
+
+/// 客户端证书及私钥
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ClientIdentity {
+    /// PEM 证书（可以包含中间证书）和 PKCS#8 PEM 私钥
+    Pem { cert_path: String, key_path: String },
+    /// PKCS#12（.p12 / .pfx）文件
+    Pkcs12 {
+        path: String,
+        #[serde(default)]
+        passphrase: String,
+    },
+}
+
+impl ClientIdentity {
+    fn load(&self) -> Result<native_tls::Identity, TlsError> {
+        let read = |path: &str| {
+            std::fs::read(path.trim())
+                .map_err(|e| TlsError::ClientIdentity(format!("{}: {}", path, e)))
+        };
+
+        match self {
+            ClientIdentity::Pem { cert_path, key_path } => {
+                let cert = read(cert_path)?;
+                let key = read(key_path)?;
+
+                // native-tls 只接受 PKCS#8 私钥，提前给出明确的提示
+                let key_text = String::from_utf8_lossy(&key);
+                if key_text.contains("BEGIN RSA PRIVATE KEY") || key_text.contains("BEGIN EC PRIVATE KEY") {
+                    return Err(TlsError::ClientIdentity(format!(
+                        "{} 不是 PKCS#8 格式的私钥，可以用 openssl pkcs8 -topk8 -nocrypt 转换",
+                        key_path
+                    )));
+                }
+
+                native_tls::Identity::from_pkcs8(&cert, &key)
+                    .map_err(|e| TlsError::ClientIdentity(format!("{}: {}", cert_path, e)))
+            }
+            ClientIdentity::Pkcs12 { path, passphrase } => {
+                let der = read(path)?;
+                native_tls::Identity::from_pkcs12(&der, passphrase)
+                    .map_err(|e| TlsError::ClientIdentity(format!("{}: {}", path, e)))
+            }
+        }
+    }
+}
+
+/// 服务器出示的证书指纹，供用户在设置中固定
+#[derive(Clone, Serialize, Debug)]
+pub struct CertificateInfo {
+    /// 整张证书的 SHA-256 指纹，形如 `AB:CD:...`
+    pub sha256: String,
+    /// 公钥（SPKI）的 SHA-256 指纹，形如 `sha256/<base64>`
+    pub spki_sha256: Option<String>,
+}
+
+impl fmt::Display for CertificateInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SHA-256 {}", self.sha256)?;
+        if let Some(spki) = &self.spki_sha256 {
+            write!(f, "，公钥 {}", spki)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum TlsError {
+    /// 读取或解析 CA 证书失败
+    CaBundle(String),
+    /// 证书指纹格式不正确
+    InvalidPin(String),
+    /// 读取客户端证书或私钥失败
+    ClientIdentity(String),
+    /// TLS 握手失败（证书不受信任、协议不匹配等）
+    Handshake(String),
+    /// 服务器证书与固定的指纹不一致
+    PinMismatch(CertificateInfo),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::CaBundle(e) => write!(f, "加载 CA 证书失败: {}", e),
+            TlsError::InvalidPin(pin) => write!(f, "证书指纹格式不正确: {}", pin),
+            TlsError::ClientIdentity(e) => write!(f, "加载客户端证书失败: {}", e),
+            TlsError::Handshake(e) => write!(f, "TLS 握手失败: {}", e),
+            TlsError::PinMismatch(actual) => {
+                write!(f, "服务器证书与固定的指纹不一致，实际为 {}", actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+impl TlsError {
+    /// 配置错误和指纹不一致重试也不会成功
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, TlsError::Handshake(_))
+    }
+}
+
+/// 固定的指纹
+enum Pin {
+    Certificate(Vec<u8>),
+    Spki(Vec<u8>),
+}
+
+impl Pin {
+    fn parse(pin: &str) -> Result<Self, TlsError> {
+        let pin = pin.trim();
+        let invalid = || TlsError::InvalidPin(pin.to_string());
+
+        if let Some(encoded) = pin.strip_prefix("sha256/") {
+            let digest = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| invalid())?;
+            if digest.len() != 32 {
+                return Err(invalid());
+            }
+            return Ok(Pin::Spki(digest));
+        }
+
+        let hex: String = pin.chars().filter(|c| !matches!(c, ':' | ' ')).collect();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let digest = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        Ok(Pin::Certificate(digest))
+    }
+
+    fn matches(&self, der: &[u8]) -> bool {
+        match self {
+            Pin::Certificate(digest) => Sha256::digest(der).as_slice() == digest.as_slice(),
+            Pin::Spki(digest) => extract_spki(der)
+                .is_some_and(|spki| Sha256::digest(spki).as_slice() == digest.as_slice()),
+        }
+    }
+}
+
+impl CertificateInfo {
+    fn from_der(der: &[u8]) -> Self {
+        let sha256 = Sha256::digest(der)
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":");
+        let spki_sha256 = extract_spki(der).map(|spki| {
+            format!(
+                "sha256/{}",
+                base64::engine::general_purpose::STANDARD.encode(Sha256::digest(spki))
+            )
+        });
+        Self { sha256, spki_sha256 }
+    }
+}
+
+/// 读取 PEM 文件中的所有证书
+fn load_ca_bundle(path: &str) -> Result<Vec<native_tls::Certificate>, TlsError> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| TlsError::CaBundle(format!("{}: {}", path, e)))?;
+
+    let mut certificates = Vec::new();
+    let mut rest = content.as_str();
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(end) = rest[start..].find(END) else {
+            break;
+        };
+        let block = &rest[start..start + end + END.len()];
+        let certificate = native_tls::Certificate::from_pem(block.as_bytes())
+            .map_err(|e| TlsError::CaBundle(format!("{}: {}", path, e)))?;
+        certificates.push(certificate);
+        rest = &rest[start + end + END.len()..];
+    }
+
+    if certificates.is_empty() {
+        return Err(TlsError::CaBundle(format!("{} 中没有找到 PEM 证书", path)));
+    }
+    Ok(certificates)
+}
+
+fn build_connector(options: &TlsOptions, pinned: bool) -> Result<native_tls::TlsConnector, TlsError> {
+    let mut builder = native_tls::TlsConnector::builder();
+
+    if let Some(path) = options.ca_bundle_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        for certificate in load_ca_bundle(path.trim())? {
+            builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some(identity) = &options.client_identity {
+        builder.identity(identity.load()?);
+    }
+
+    // 固定指纹时由指纹校验代替证书链校验，这样自签名证书也能使用
+    if options.accept_invalid_certs || pinned {
+        builder.danger_accept_invalid_certs(true);
+        builder.danger_accept_invalid_hostnames(true);
+    }
+
+    builder.build().map_err(|e| TlsError::Handshake(e.to_string()))
+}
+
+/// 在 TCP 连接上完成 TLS 握手，并在发送任何数据之前校验固定的指纹
+pub async fn connect(
+    domain: &str,
+    tcp: TcpStream,
+    options: &TlsOptions,
+) -> Result<(tokio_native_tls::TlsStream<TcpStream>, Option<CertificateInfo>), TlsError> {
+    let pin = options
+        .pin_sha256
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+        .map(Pin::parse)
+        .transpose()?;
+
+    let connector = build_connector(options, pin.is_some())?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(domain, tcp)
+        .await
+        .map_err(|e| {
+            if options.client_identity.is_some() {
+                TlsError::Handshake(format!("{}（已配置客户端证书，请确认服务器信任该证书）", e))
+            } else {
+                TlsError::Handshake(e.to_string())
+            }
+        })?;
+
+    let der = tls
+        .get_ref()
+        .peer_certificate()
+        .ok()
+        .flatten()
+        .and_then(|certificate| certificate.to_der().ok());
+
+    if let Some(pin) = &pin {
+        match &der {
+            Some(der) if pin.matches(der) => {}
+            Some(der) => return Err(TlsError::PinMismatch(CertificateInfo::from_der(der))),
+            None => return Err(TlsError::Handshake("服务器没有提供证书".to_string())),
+        }
+    }
+
+    Ok((tls, der.as_deref().map(CertificateInfo::from_der)))
+}
+
+/// 不做任何校验地握手，只为取得服务器证书的指纹
+pub async fn probe_certificate(domain: &str, tcp: TcpStream) -> Option<CertificateInfo> {
+    let options = TlsOptions {
+        accept_invalid_certs: true,
+        ..TlsOptions::default()
+    };
+    connect(domain, tcp, &options).await.ok().and_then(|(_, info)| info)
+}
+
+/// DER 编码中的一个元素
+struct DerElement<'a> {
+    tag: u8,
+    /// 元素内容（不含标签和长度）
+    content: &'a [u8],
+    /// 整个元素（含标签和长度）
+    raw: &'a [u8],
+    /// 元素之后剩余的数据
+    rest: &'a [u8],
+}
+
+/// 读取一个 DER 元素
+fn der_element(input: &[u8]) -> Option<DerElement<'_>> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+
+    let (length, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = input.get(2..2 + count)?;
+        let length = bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (length, 2 + count)
+    };
+
+    let end = header.checked_add(length)?;
+    let raw = input.get(..end)?;
+    Some(DerElement {
+        tag,
+        content: &raw[header..],
+        raw,
+        rest: &input[end..],
+    })
+}
+
+/// 从 DER 证书中取出 SubjectPublicKeyInfo
+fn extract_spki(der: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let certificate = der_element(der).filter(|e| e.tag == SEQUENCE)?;
+    let tbs = der_element(certificate.content).filter(|e| e.tag == SEQUENCE)?;
+
+    let mut rest = tbs.content;
+    if rest.first() == Some(&VERSION) {
+        rest = der_element(rest)?.rest;
+    }
+    // 跳过 serialNumber、signature、issuer、validity、subject
+    for _ in 0..5 {
+        rest = der_element(rest)?.rest;
+    }
+
+    der_element(rest)
+        .filter(|e| e.tag == SEQUENCE)
+        .map(|spki| spki.raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `openssl req -x509 -newkey ec -subj /CN=gotify.test` 生成的自签名证书
+    const CERTIFICATE: &str = "MIIBgzCCASmgAwIBAgIUNdsdww3zyZZszw5Y0KPR6C8o6nQwCgYIKoZIzj0EAwIwFjEUMBIGA1UEAwwLZ290aWZ5LnRlc3QwIBcNMjYxMDE4MDYyNjA3WhgPMjEyNjA5MjQwNjI2MDdaMBYxFDASBgNVBAMMC2dvdGlmeS50ZXN0MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE6i8QAa6KYiCiWwPul6PBdvgTaEMQgavZXBd8t9pIAZJ6pmw0n/ECLNkVFhDOqioWiD8j9yFPLSML79wLYH/5M6NTMFEwHQYDVR0OBBYEFBuckjaeD0y1E6Q9KAIdNWnWAFoNMB8GA1UdIwQYMBaAFBuckjaeD0y1E6Q9KAIdNWnWAFoNMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhAPJZmKVaug6+J0tI7v0P/kIvzkQoPrWdxrsroJ2Ti9+OAiB0XI8hMQeNoAkupDKKDo7HJAn4LM6qIqEi34RQ07FvyQ==";
+    /// `openssl x509 -fingerprint -sha256`
+    const CERTIFICATE_SHA256: &str =
+        "22:93:B2:65:30:24:2C:B6:62:3E:1B:A4:0C:BA:A8:AE:61:40:76:66:64:E7:21:C5:81:00:B0:16:4D:6D:7A:F1";
+    /// `openssl x509 -pubkey | openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64`
+    const SPKI_SHA256: &str = "sha256/4JWoNFPAkX5ZhCepCKeUPmcqcomGgcI3PqIqOSJBvo0=";
+
+    fn certificate() -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD.decode(CERTIFICATE).unwrap()
+    }
+
+    #[test]
+    fn reads_short_form_length() {
+        let element = der_element(&[0x04, 0x02, 0xaa, 0xbb, 0xcc]).unwrap();
+        assert_eq!(element.tag, 0x04);
+        assert_eq!(element.content, &[0xaa, 0xbb]);
+        assert_eq!(element.raw, &[0x04, 0x02, 0xaa, 0xbb]);
+        assert_eq!(element.rest, &[0xcc]);
+    }
+
+    #[test]
+    fn reads_long_form_length() {
+        let mut input = vec![0x30, 0x82, 0x01, 0x00];
+        input.extend(std::iter::repeat(0x55).take(0x100));
+        let element = der_element(&input).unwrap();
+        assert_eq!(element.content.len(), 0x100);
+        assert!(element.rest.is_empty());
+
+        let element = der_element(&[0x04, 0x81, 0x01, 0x7f]).unwrap();
+        assert_eq!(element.content, &[0x7f]);
+    }
+
+    #[test]
+    fn rejects_truncated_or_unsupported_lengths() {
+        assert!(der_element(&[]).is_none());
+        assert!(der_element(&[0x30]).is_none());
+        assert!(der_element(&[0x30, 0x03, 0x01, 0x02]).is_none());
+        assert!(der_element(&[0x30, 0x82, 0x01]).is_none());
+        assert!(der_element(&[0x30, 0x82, 0x01, 0x00, 0x00]).is_none());
+        // 不定长和超过 4 字节的长度
+        assert!(der_element(&[0x30, 0x80, 0x00, 0x00]).is_none());
+        assert!(der_element(&[0x30, 0x85, 0x01, 0x00, 0x00, 0x00, 0x00]).is_none());
+    }
+
+    #[test]
+    fn extracts_fingerprints() {
+        let info = CertificateInfo::from_der(&certificate());
+        assert_eq!(info.sha256, CERTIFICATE_SHA256);
+        assert_eq!(info.spki_sha256.as_deref(), Some(SPKI_SHA256));
+    }
+
+    #[test]
+    fn spki_of_truncated_certificate_is_none() {
+        let der = certificate();
+        assert!(extract_spki(&der[..der.len() / 2]).is_none());
+        assert!(CertificateInfo::from_der(&der[..100]).spki_sha256.is_none());
+    }
+
+    #[test]
+    fn matches_pins() {
+        let der = certificate();
+        assert!(Pin::parse(CERTIFICATE_SHA256).unwrap().matches(&der));
+        assert!(Pin::parse(&CERTIFICATE_SHA256.replace(':', "").to_lowercase())
+            .unwrap()
+            .matches(&der));
+        assert!(Pin::parse(&format!(" {} ", SPKI_SHA256)).unwrap().matches(&der));
+
+        let other = Pin::parse(&"00".repeat(32)).unwrap();
+        assert!(!other.matches(&der));
+        let other_spki = Pin::parse(&format!("sha256/{}", "A".repeat(43) + "=")).unwrap();
+        assert!(!other_spki.matches(&der));
+    }
+
+    #[test]
+    fn rejects_invalid_pins() {
+        for pin in ["", "abcd", "sha256/not-base64!", "sha256/AAAA", &"zz".repeat(32)] {
+            assert!(matches!(Pin::parse(pin), Err(TlsError::InvalidPin(_))), "{}", pin);
+        }
+    }
+}